@@ -0,0 +1,19 @@
+// Flat, banded lighting for a toon look: each fragment snaps to one of a few brightness steps
+#import bevy_pbr::forward_io::VertexOutput
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+
+// Roughly where the sun in the sample scene sits
+const LIGHT_DIRECTION: vec3<f32> = vec3<f32>(0.4, 1.0, 0.3);
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let light = dot(normalize(mesh.world_normal), normalize(LIGHT_DIRECTION));
+    var band = 0.45;
+    if light > 0.7 {
+        band = 1.0;
+    } else if light > 0.2 {
+        band = 0.7;
+    }
+    return vec4<f32>(color.rgb * band, color.a);
+}
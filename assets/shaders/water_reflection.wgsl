@@ -0,0 +1,18 @@
+// A water surface that shows whatever the reflection camera sees, tinted with the water color
+#import bevy_pbr::forward_io::VertexOutput
+#import bevy_pbr::mesh_view_bindings::view
+
+@group(1) @binding(0) var<uniform> tint: vec4<f32>;
+@group(1) @binding(1) var reflection_texture: texture_2d<f32>;
+@group(1) @binding(2) var reflection_sampler: sampler;
+
+// How much of the water color shows through the reflection
+const TINT_STRENGTH: f32 = 0.5;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    // The reflection camera sees the world upside down, so flip the screen position to line the two up
+    let screen = (mesh.position.xy - view.viewport.xy) / view.viewport.zw;
+    let reflected = textureSample(reflection_texture, reflection_sampler, vec2<f32>(screen.x, 1.0 - screen.y));
+    return vec4<f32>(mix(reflected.rgb, tint.rgb, TINT_STRENGTH), tint.a);
+}
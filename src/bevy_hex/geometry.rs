@@ -1,14 +1,48 @@
-use super::hex::HexCoord;
+use std::collections::HashSet;
+
+use super::hex::{Direction, HexCoord, DIRECTIONS};
 
 /// The ratio between a circle touching the points of a hex grid (the outer radius),
 /// and a circle touching the edges of a hex grid (the inner radius).
 /// Calculated as sqrt(3) / 2;
 pub const HEX_INNER_RADIUS_RATIO: f32 = 0.866_025_4;
 
+/// The outer radius of a hexagon, when there's no reason to pick anything else.
+/// Meshes and placement should both use this, or tiles will overlap or leave gaps
+pub const DEFAULT_HEX_RADIUS: f32 = 1.0;
+
+/// How far the top face of a beveled tile is scaled in, leaving the rest of the radius for the slope
+pub const DEFAULT_BEVEL_FACTOR: f32 = 0.9;
+
+/// How far below its top a beveled tile's skirt reaches, unless asked otherwise
+pub const DEFAULT_SKIRT_DEPTH: f32 = 10.;
+
+/// How hex coordinates are laid out in world space
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LayoutMode {
+    /// The original layout, which cancels out the rhombus shape every other row so a square range of
+    /// coordinates produces a roughly square map. Neighboring coordinates aren't always neighboring tiles.
+    #[default]
+    Sheared,
+    /// True axial layout, where each coordinate's neighbors are exactly the tiles surrounding it
+    Hexagonal,
+}
+
 /// Generate a point located at the center of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`.
 /// The parameters are used to compose larger effects like beveling
 #[must_use]
 pub fn center(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
+    center_with_layout(LayoutMode::Sheared, radius, c, offset)
+}
+
+/// Generate a point located at the center of a hexagon at `c`, laid out according to `layout`
+#[must_use]
+pub fn center_with_layout(
+    layout: LayoutMode,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
     // Get floating point hex-coords
     let (qf, rf) = (c.q as f32, c.r as f32);
     // We need an outer and inner radius
@@ -19,7 +53,11 @@ pub fn center(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
     // Shift over by half a unit for each row
     let row_adjustment = 0.5 * rf;
     // This produces a rhombus, use integer division to cancel this out on every other row and get "roughly" a grid
-    let rhombus_adjustment = -(c.r / 2) as f32;
+    let rhombus_adjustment = match layout {
+        LayoutMode::Sheared => -(c.r / 2) as f32,
+        // The rhombus is exactly what a true hex grid looks like, so leave it alone
+        LayoutMode::Hexagonal => 0.,
+    };
     // Scale the whole thing up by twice the inner radius to get our x coordinate
     let x = (start + row_adjustment + rhombus_adjustment) * inner * 2.;
     // Each row moves us by 1.5 times the outer radius along the z axis
@@ -29,80 +67,213 @@ pub fn center(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
     [x + offset[0], 0. + offset[1], z + offset[2]]
 }
 
+/// Find the hexagon containing the world space point `pos`, on a grid with hexagons of size `radius` laid out
+/// according to `layout`. This is the inverse of [center_with_layout], ignoring height
+#[must_use]
+pub fn hex_at_point(layout: LayoutMode, radius: f32, pos: &[f32; 3]) -> HexCoord {
+    let (outer, inner) = (radius, radius * HEX_INNER_RADIUS_RATIO);
+    // Rows are easy, they're just spaced out along the z axis
+    let rf = pos[2] / (outer * 1.5);
+    // Undo the scaling and the half unit shift per row, which leaves us on a true hex grid
+    let qf = pos[0] / (inner * 2.) - 0.5 * rf;
+    let c = HexCoord::round(qf, rf);
+    match layout {
+        // Now that we know which row we're in, we can undo the rhombus adjustment.
+        // This has to be the same truncating division as in `center`, so negative odd rows shift the same way
+        LayoutMode::Sheared => HexCoord::new(c.q + c.r / 2, c.r),
+        LayoutMode::Hexagonal => c,
+    }
+}
+
+/// Which way round hexagons sit in world space
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Orientation {
+    /// The original orientation, with flat edges facing north and south along the x axis, and points east and west
+    #[default]
+    FlatTop,
+    /// Turned a quarter turn, with points facing north and south along the x axis, and flat edges east and west
+    PointyTop,
+}
+
+impl Orientation {
+    /// Turn a flat top vector to match this orientation, by rotating a quarter turn around the y axis if need be
+    #[must_use]
+    pub fn rotate(self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self {
+            Orientation::FlatTop => [x, y, z],
+            Orientation::PointyTop => [z, y, -x],
+        }
+    }
+}
+
+/// Generate a point located at the center of a hexagon at `c`, with hexagons turned to `orientation`.
+/// The whole grid turns with the hexagons, so they still fit together
+#[must_use]
+pub fn center_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
+    let [x, y, z] = orientation.rotate(center(radius, c, &[0., 0., 0.]));
+    [x + offset[0], y + offset[1], z + offset[2]]
+}
+
+/// A corner `(dx, dz)` away from the center of a flat top hexagon, turned to `orientation`
+fn corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+    (dx, dz): (f32, f32),
+) -> [f32; 3] {
+    // Start from the center of our hexagon
+    let center = center_with_orientation(orientation, radius, c, offset);
+    // And move out to the corner, turned the same way as the grid
+    let [dx, _, dz] = orientation.rotate([dx, 0., dz]);
+    [center[0] + dx, center[1], center[2] + dz]
+}
+
 /// Generate a pointed located at the eastern corner of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`
 #[must_use]
 pub fn east_corner(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
-    // Start from the center of our hexagon
-    let center = center(radius, c, offset);
-    // And move along the z axis for "east" by our radius
-    [center[0] + 0., center[1] + 0., center[2] + radius]
+    east_corner_with_orientation(Orientation::FlatTop, radius, c, offset)
+}
+
+/// Like [east_corner], with hexagons turned to `orientation`
+#[must_use]
+pub fn east_corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
+    // Move along the z axis for "east" by our radius
+    corner_with_orientation(orientation, radius, c, offset, (0., radius))
 }
 
 /// Generate a pointed located at the western corner of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`
 #[must_use]
 pub fn west_corner(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
-    // Start from the center of our hexagon
-    let center = center(radius, c, offset);
-    // And move along the z axis for "west" by our radius
-    [center[0] + 0., center[1] + 0., center[2] - radius]
+    west_corner_with_orientation(Orientation::FlatTop, radius, c, offset)
+}
+
+/// Like [west_corner], with hexagons turned to `orientation`
+#[must_use]
+pub fn west_corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
+    // Move along the z axis for "west" by our radius
+    corner_with_orientation(orientation, radius, c, offset, (0., -radius))
 }
 
 /// Generate a pointed located at the north-east corner of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`
 #[must_use]
 pub fn north_east_corner(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
-    // Start from the center of our hexagon
-    let center = center(radius, c, offset);
+    north_east_corner_with_orientation(Orientation::FlatTop, radius, c, offset)
+}
+
+/// Like [north_east_corner], with hexagons turned to `orientation`
+#[must_use]
+pub fn north_east_corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
     let inner = radius * HEX_INNER_RADIUS_RATIO;
-    // And move along the x axis (for "north") to be aligned with the top edge (i.e. the inner radius)
+    // Move along the x axis (for "north") to be aligned with the top edge (i.e. the inner radius)
     // and along the z axis (for "east"), but not as far as the east corner
-    [center[0] + inner, center[1] + 0., center[2] + 0.5 * radius]
+    corner_with_orientation(orientation, radius, c, offset, (inner, 0.5 * radius))
 }
 
 /// Generate a pointed located at the north-west corner of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`
 #[must_use]
 pub fn north_west_corner(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
-    // Start from the center of our hexagon
-    let center = center(radius, c, offset);
+    north_west_corner_with_orientation(Orientation::FlatTop, radius, c, offset)
+}
+
+/// Like [north_west_corner], with hexagons turned to `orientation`
+#[must_use]
+pub fn north_west_corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
     let inner = radius * HEX_INNER_RADIUS_RATIO;
-    // And move along the x axis (for "north") to be aligned with the top edge (i.e. the inner radius)
-    // and along the z axis (for "west"), but not as far as the east corner
-    [center[0] + inner, center[1] + 0., center[2] - 0.5 * radius]
+    // Move along the x axis (for "north") to be aligned with the top edge (i.e. the inner radius)
+    // and along the z axis (for "west"), but not as far as the west corner
+    corner_with_orientation(orientation, radius, c, offset, (inner, -0.5 * radius))
 }
 
 /// Generate a pointed located at the south-east corner of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`
 #[must_use]
 pub fn south_east_corner(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
-    // Start from the center of our hexagon
-    let center = center(radius, c, offset);
+    south_east_corner_with_orientation(Orientation::FlatTop, radius, c, offset)
+}
+
+/// Like [south_east_corner], with hexagons turned to `orientation`
+#[must_use]
+pub fn south_east_corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
     let inner = radius * HEX_INNER_RADIUS_RATIO;
-    // And move along the x axis (for "south") to be aligned with the top edge (i.e. the inner radius)
+    // Move along the x axis (for "south") to be aligned with the bottom edge (i.e. the inner radius)
     // and along the z axis (for "east"), but not as far as the east corner
-    [center[0] - inner, center[1] + 0., center[2] + 0.5 * radius]
+    corner_with_orientation(orientation, radius, c, offset, (-inner, 0.5 * radius))
 }
 
 /// Generate a pointed located at the south-west corner of a hexagon at `c`, on a grid with hexagons of size `radius`, shifted by `offset`
 #[must_use]
 pub fn south_west_corner(radius: f32, c: &HexCoord, offset: &[f32; 3]) -> [f32; 3] {
-    // Start from the center of our hexagon
-    let center = center(radius, c, offset);
+    south_west_corner_with_orientation(Orientation::FlatTop, radius, c, offset)
+}
+
+/// Like [south_west_corner], with hexagons turned to `orientation`
+#[must_use]
+pub fn south_west_corner_with_orientation(
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) -> [f32; 3] {
     let inner = radius * HEX_INNER_RADIUS_RATIO;
-    // And move along the x axis (for "south") to be aligned with the top edge (i.e. the inner radius)
-    // and along the z axis (for "west"), but not as far as the east corner
-    [center[0] - inner, center[1] + 0., center[2] - 0.5 * radius]
+    // Move along the x axis (for "south") to be aligned with the bottom edge (i.e. the inner radius)
+    // and along the z axis (for "west"), but not as far as the west corner
+    corner_with_orientation(orientation, radius, c, offset, (-inner, -0.5 * radius))
 }
 
 /// Fill `pts` with the points around the edge of a flat hexagon of a specific radius at a specific coordinate
 pub fn flat_hexagon_ring(pts: &mut Vec<[f32; 3]>, radius: f32, c: &HexCoord, offset: &[f32; 3]) {
+    flat_hexagon_ring_with_orientation(pts, Orientation::FlatTop, radius, c, offset);
+}
+
+/// Like [flat_hexagon_ring], with hexagons turned to `orientation`
+pub fn flat_hexagon_ring_with_orientation(
+    pts: &mut Vec<[f32; 3]>,
+    orientation: Orientation,
+    radius: f32,
+    c: &HexCoord,
+    offset: &[f32; 3],
+) {
+    let o = orientation;
     pts.extend(
         [
-            east_corner(radius, c, offset), // Each of the corners, counter-clockwise from the east corner
-            north_east_corner(radius, c, offset), // ...
-            north_west_corner(radius, c, offset), // ...
-            west_corner(radius, c, offset), // ...
-            south_west_corner(radius, c, offset), // ...
-            south_east_corner(radius, c, offset), // ...
-            east_corner(radius, c, offset), // We include the east corner an extra time,
-                                            // so we don't have to mess around with modulus
+            east_corner_with_orientation(o, radius, c, offset), // Each of the corners, counter-clockwise from the east corner
+            north_east_corner_with_orientation(o, radius, c, offset), // ...
+            north_west_corner_with_orientation(o, radius, c, offset), // ...
+            west_corner_with_orientation(o, radius, c, offset), // ...
+            south_west_corner_with_orientation(o, radius, c, offset), // ...
+            south_east_corner_with_orientation(o, radius, c, offset), // ...
+            east_corner_with_orientation(o, radius, c, offset), // We include the east corner an extra time,
+                                                                // so we don't have to mess around with modulus
         ]
         .iter(),
     );
@@ -126,7 +297,7 @@ pub fn flat_hexagon_normals(normals: &mut Vec<[f32; 3]>) {
 /// Fill `idx` with the indices to create a hexagon when interpreted as a triangle list
 pub fn flat_hexagon_indices(idx: &mut Vec<u32>) {
     // Each of the six faces
-    for i in 0..=6 {
+    for i in 0..6 {
         //           first-time     second-time
         idx.push(0); // Center
         idx.push(i + 1); // Point       East           North-east
@@ -136,6 +307,18 @@ pub fn flat_hexagon_indices(idx: &mut Vec<u32>) {
 
 /// Fill `points` with the points for a beveled `radius` hexagon, beveled by `factor`, at point `c`
 pub fn bevel_hexagon_points(points: &mut Vec<[f32; 3]>, radius: f32, factor: f32, c: &HexCoord) {
+    bevel_hexagon_points_with_skirt(points, radius, factor, DEFAULT_SKIRT_DEPTH, c);
+}
+
+/// Fill `points` with the points for a beveled hexagon, like [bevel_hexagon_points], with a skirt reaching
+/// `skirt_depth` below the top. It only needs to reach as far down as the lowest neighbor could be
+pub fn bevel_hexagon_points_with_skirt(
+    points: &mut Vec<[f32; 3]>,
+    radius: f32,
+    factor: f32,
+    skirt_depth: f32,
+    c: &HexCoord,
+) {
     let inner_radius = radius * factor;
     // Populate the points for the top face, as a slightly scaled hexagon
     flat_hexagon_points(points, inner_radius, c);
@@ -148,7 +331,7 @@ pub fn bevel_hexagon_points(points: &mut Vec<[f32; 3]>, radius: f32, factor: f32
     flat_hexagon_ring(points, radius, c, &offset);
 
     // Now, add points much lower, so we can create skirts so if hexagons are offset we don't see gaps
-    let offset = [0., -10., 0.];
+    let offset = [0., -skirt_depth, 0.];
     // Add skirts
     flat_hexagon_ring(points, radius, c, &offset);
 }
@@ -167,6 +350,24 @@ pub fn bevel_hexagon_normals(normals: &mut Vec<[f32; 3]>) {
     flat_hexagon_ring(normals, 1., c, &offset);
 }
 
+/// Fill `uvs` with texture coordinates for a beveled hexagon, one for each of the points from [bevel_hexagon_points].
+/// The top face is the circle inscribed in the texture, and the slope and skirt wrap once around it, with U running
+/// counter-clockwise from the east corner and V running down the side
+pub fn bevel_hexagon_uvs(uvs: &mut Vec<[f32; 2]>) {
+    // The top face: its center, then each corner (and the repeated east corner) out on the circle
+    uvs.push([0.5, 0.5]);
+    for i in 0..7 {
+        let angle = std::f32::consts::FRAC_PI_3 * i as f32;
+        uvs.push([0.5 + 0.5 * angle.cos(), 0.5 - 0.5 * angle.sin()]);
+    }
+    // The slope ring sits at the top of the side, and the skirt at the bottom
+    for v in [0., 1.] {
+        for i in 0..7 {
+            uvs.push([i as f32 / 6., v]);
+        }
+    }
+}
+
 /// Fill `idx` with indices to draw a quad using the 4 provided corners
 pub fn quad_indices(
     idx: &mut Vec<u32>,
@@ -187,13 +388,671 @@ pub fn bevel_hexagon_indices(idx: &mut Vec<u32>) {
     flat_hexagon_indices(idx);
 
     // Add slopes
-    for i in 0..=6 {
+    for i in 0..6 {
         // Insert a quad, using the inner beveled hex, and the outer sloped hex
         quad_indices(idx, i + 1, i + 2, i + 8, i + 9);
     }
     // Add a skirt
-    for i in 0..=6 {
+    for i in 0..6 {
         // Insert a quad using the outer sloped hex and the bottom base hex
         quad_indices(idx, i + 8, i + 9, i + 15, i + 16);
     }
 }
+
+/// Fill `points` with a beveled hexagon like [bevel_hexagon_points], but with three points of its own for every
+/// triangle, so no point is shared between faces. Draw it with the indices `0..points.len()`
+pub fn bevel_hexagon_unshared_points(
+    points: &mut Vec<[f32; 3]>,
+    radius: f32,
+    factor: f32,
+    c: &HexCoord,
+) {
+    let (mut shared, mut idx) = (vec![], vec![]);
+    bevel_hexagon_points(&mut shared, radius, factor, c);
+    bevel_hexagon_indices(&mut idx);
+    points.extend(idx.iter().map(|i| shared[*i as usize]));
+}
+
+/// Fill `normals` with the true normal of each triangle in `points`, taken three at a time as from
+/// [bevel_hexagon_unshared_points]. Every corner of a triangle gets the same normal, so each face lights evenly and
+/// the edges between faces stay crisp
+pub fn bevel_hexagon_flat_normals(normals: &mut Vec<[f32; 3]>, points: &[[f32; 3]]) {
+    for triangle in points.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        // Our triangles wind so that this cross product points out of the tile
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        let n = [n[0] / length, n[1] / length, n[2] / length];
+        normals.extend([n, n, n]);
+    }
+}
+
+/// Collect the tiles within `ra` steps of `a` that are also within `rb` steps of `b`
+#[must_use]
+pub fn intersect_ranges(a: &HexCoord, ra: usize, b: &HexCoord, rb: usize) -> Vec<HexCoord> {
+    // Walk whichever disk is smaller, and keep the tiles that land inside the other one
+    let ((small, rs), (large, rl)) = if ra <= rb {
+        ((a, ra), (b, rb))
+    } else {
+        ((b, rb), (a, ra))
+    };
+    if small.distance(large) > rs + rl {
+        return vec![];
+    }
+    small
+        .range(rs)
+        .into_iter()
+        .filter(|c| c.distance(large) <= rl)
+        .collect()
+}
+
+/// Sort `tiles` into reading order (by offset row, then offset column), for writing grid based file formats
+#[must_use]
+pub fn row_major(tiles: &[HexCoord]) -> Vec<HexCoord> {
+    let mut sorted = tiles.to_vec();
+    sorted.sort_by_key(|c| {
+        let (col, row) = c.to_offset();
+        (row, col)
+    });
+    sorted
+}
+
+/// The world space direction of one step along the +q, +r, and +s axes, on a hexagonal layout of size `radius`.
+/// Each axis step keeps one of the other components fixed, so the three are 120 degrees apart
+#[must_use]
+pub fn axis_vectors(radius: f32) -> [[f32; 3]; 3] {
+    let origin = HexCoord::origin();
+    let zero = [0., 0., 0.];
+    let base = center_with_layout(LayoutMode::Hexagonal, radius, &origin, &zero);
+    [
+        HexCoord::new(1, 0),  // +q, -s
+        HexCoord::new(-1, 1), // +r, -q
+        HexCoord::new(0, -1), // +s, -r
+    ]
+    .map(|c| {
+        let p = center_with_layout(LayoutMode::Hexagonal, radius, &c, &zero);
+        [p[0] - base[0], p[1] - base[1], p[2] - base[2]]
+    })
+}
+
+/// Grow `region` outward by one step, returning it along with every neighbor of its tiles
+#[must_use]
+pub fn expand(region: &HashSet<HexCoord>) -> HashSet<HexCoord> {
+    let mut grown = region.clone();
+    for c in region {
+        grown.extend(c.neighbors());
+    }
+    grown
+}
+
+/// How many tile edges around `region` face a tile outside of it; the more spread out a region, the higher this is
+#[must_use]
+pub fn perimeter(region: &HashSet<HexCoord>) -> usize {
+    region
+        .iter()
+        .map(|c| c.neighbors().filter(|n| !region.contains(n)).count())
+        .sum()
+}
+
+/// The axis aligned bounding box, as `(min, max)`, of the tiles in a region on a grid with hexagons of size `radius`
+/// laid out according to `layout`. This covers the whole of each tile, not just the centers, and is `None` for an
+/// empty region
+#[must_use]
+pub fn region_aabb(
+    layout: LayoutMode,
+    radius: f32,
+    tiles: &[HexCoord],
+) -> Option<([f32; 3], [f32; 3])> {
+    let inner = radius * HEX_INNER_RADIUS_RATIO;
+    tiles.iter().fold(None, |aabb, c| {
+        let p = center_with_layout(layout, radius, c, &[0., 0., 0.]);
+        let (lo, hi) = (
+            [p[0] - inner, p[1], p[2] - radius],
+            [p[0] + inner, p[1], p[2] + radius],
+        );
+        Some(match aabb {
+            None => (lo, hi),
+            Some((min, max)) => (
+                [0, 1, 2].map(|i: usize| f32::min(min[i], lo[i])),
+                [0, 1, 2].map(|i: usize| f32::max(max[i], hi[i])),
+            ),
+        })
+    })
+}
+
+/// The edges where `a` touches `b`, as each tile in `a` paired with the direction of its neighbor in `b`.
+/// A tile in both regions doesn't border itself, but still borders any other tile of `b`
+#[must_use]
+pub fn shared_border(a: &HashSet<HexCoord>, b: &HashSet<HexCoord>) -> Vec<(HexCoord, Direction)> {
+    a.iter()
+        .flat_map(|c| {
+            DIRECTIONS
+                .iter()
+                .filter(|dir| b.contains(&c.neighbor(**dir)))
+                .map(|dir| (*c, *dir))
+        })
+        .collect()
+}
+
+/// The two ends of the edge between neighboring tiles `a` and `b`, on a grid with hexagons of size `radius` laid out
+/// according to `layout`. The edge runs across the midpoint between their centers, at right angles to the line joining
+/// them. Only [LayoutMode::Hexagonal] is sure to put every pair of neighbors side by side
+#[must_use]
+pub fn edge_between(layout: LayoutMode, radius: f32, a: &HexCoord, b: &HexCoord) -> [[f32; 3]; 2] {
+    let [ax, ay, az] = center_with_layout(layout, radius, a, &[0., 0., 0.]);
+    let [bx, by, bz] = center_with_layout(layout, radius, b, &[0., 0., 0.]);
+    let mid = [(ax + bx) / 2., (ay + by) / 2., (az + bz) / 2.];
+    let (dx, dz) = (bx - ax, bz - az);
+    let length = (dx * dx + dz * dz).sqrt();
+    // An edge is as long as the outer radius, so reach half of that either side of the midpoint
+    let (px, pz) = (-dz / length * radius / 2., dx / length * radius / 2.);
+    [
+        [mid[0] + px, mid[1], mid[2] + pz],
+        [mid[0] - px, mid[1], mid[2] - pz],
+    ]
+}
+
+/// The vertex data for many tiles merged into one mesh, so they can be drawn all at once
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ChunkMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// Each tile's color, repeated on every one of its vertices
+    pub colors: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+/// Merge a beveled hexagon of [DEFAULT_HEX_RADIUS] for each `(position, color)` into a single [ChunkMesh], with the
+/// middle of each tile's top face at its position, just like a tile drawn on its own at that translation
+#[must_use]
+pub fn build_chunk_mesh(tiles: &[([f32; 3], [f32; 4])]) -> ChunkMesh {
+    // Build one tile at the origin, and copy it into place for each of the others
+    let (mut points, mut normals, mut indices) = (vec![], vec![], vec![]);
+    let origin = HexCoord::origin();
+    bevel_hexagon_points(
+        &mut points,
+        DEFAULT_HEX_RADIUS,
+        DEFAULT_BEVEL_FACTOR,
+        &origin,
+    );
+    bevel_hexagon_normals(&mut normals);
+    let mut uvs = vec![];
+    bevel_hexagon_uvs(&mut uvs);
+    bevel_hexagon_indices(&mut indices);
+
+    let mut chunk = ChunkMesh::default();
+    for ([x, y, z], color) in tiles {
+        // Each tile's indices count from its own first point, so bump them past every tile before it
+        let first = chunk.positions.len() as u32;
+        chunk
+            .positions
+            .extend(points.iter().map(|p| [p[0] + x, p[1] + y, p[2] + z]));
+        chunk.normals.extend_from_slice(&normals);
+        chunk.uvs.extend_from_slice(&uvs);
+        chunk.colors.extend(points.iter().map(|_| *color));
+        chunk.indices.extend(indices.iter().map(|i| i + first));
+    }
+    chunk
+}
+
+/// Fill `points` with the points for a thin raised band around the edge of a `radius` hexagon at `c`,
+/// `width` across and `height` tall, for outlining a tile
+pub fn selection_ring_points(
+    points: &mut Vec<[f32; 3]>,
+    radius: f32,
+    width: f32,
+    height: f32,
+    c: &HexCoord,
+) {
+    let (top, bottom) = ([0., height, 0.], [0., 0., 0.]);
+    // Inner and outer edges of the top of the band
+    flat_hexagon_ring(points, radius - width, c, &top);
+    flat_hexagon_ring(points, radius, c, &top);
+    // And the same again at the bottom, for the walls
+    flat_hexagon_ring(points, radius - width, c, &bottom);
+    flat_hexagon_ring(points, radius, c, &bottom);
+}
+
+/// Fill `normals` with the normals for a selection ring
+pub fn selection_ring_normals(normals: &mut Vec<[f32; 3]>) {
+    // The top of the band points up
+    for _ in 0..14 {
+        normals.push([0., 1., 0.]);
+    }
+    // The inner wall faces in towards the tile, and the outer wall faces out
+    let c = &HexCoord::origin();
+    let mut outward = vec![];
+    flat_hexagon_ring(&mut outward, 1., c, &[0., 0., 0.]);
+    normals.extend(outward.iter().map(|[x, y, z]| [-x, -y, -z]));
+    normals.extend(outward);
+}
+
+/// Fill `idx` with the indices to construct a selection ring
+pub fn selection_ring_indices(idx: &mut Vec<u32>) {
+    let (inner_top, outer_top, inner_bottom, outer_bottom) = (0, 7, 14, 21);
+    for i in 0..6 {
+        // Top of the band
+        quad_indices(
+            idx,
+            inner_top + i,
+            inner_top + i + 1,
+            outer_top + i,
+            outer_top + i + 1,
+        );
+        // Outer wall
+        quad_indices(
+            idx,
+            outer_top + i,
+            outer_top + i + 1,
+            outer_bottom + i,
+            outer_bottom + i + 1,
+        );
+        // Inner wall, wound the other way so it faces inward
+        quad_indices(
+            idx,
+            inner_bottom + i,
+            inner_bottom + i + 1,
+            inner_top + i,
+            inner_top + i + 1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_modes_diverge_on_distant_rows() {
+        let origin = HexCoord::origin();
+        assert_eq!(
+            center_with_layout(LayoutMode::Sheared, 1., &origin, &[0., 0., 0.]),
+            center_with_layout(LayoutMode::Hexagonal, 1., &origin, &[0., 0., 0.]),
+        );
+
+        let far = HexCoord::new(3, 10);
+        let sheared = center_with_layout(LayoutMode::Sheared, 1., &far, &[0., 0., 0.]);
+        let hexagonal = center_with_layout(LayoutMode::Hexagonal, 1., &far, &[0., 0., 0.]);
+        assert_ne!(sheared, hexagonal);
+        // Rows stay at the same depth either way, only the columns move
+        assert_eq!(sheared[2], hexagonal[2]);
+        assert_eq!(sheared, center(1., &far, &[0., 0., 0.]));
+    }
+
+    #[test]
+    fn intersect_ranges_matches_brute_force() {
+        let (a, b) = (HexCoord::new(0, 0), HexCoord::new(3, -1));
+        let mut expected: Vec<_> = HexCoord::origin()
+            .range(10)
+            .into_iter()
+            .filter(|c| c.distance(&a) <= 2 && c.distance(&b) <= 3)
+            .collect();
+        let mut actual = intersect_ranges(&a, 2, &b, 3);
+        assert!(!actual.is_empty());
+
+        let key = |c: &HexCoord| (c.q, c.r);
+        expected.sort_by_key(key);
+        actual.sort_by_key(key);
+        assert_eq!(expected, actual);
+
+        assert!(intersect_ranges(&a, 1, &HexCoord::new(10, 0), 1).is_empty());
+    }
+
+    #[test]
+    fn row_major_sorts_by_row_then_column() {
+        let tiles = HexCoord::new(2, -1).range(3);
+        let sorted = row_major(&tiles);
+        assert_eq!(tiles.len(), sorted.len());
+        for pair in sorted.windows(2) {
+            let (col_a, row_a) = pair[0].to_offset();
+            let (col_b, row_b) = pair[1].to_offset();
+            assert!((row_a, col_a) < (row_b, col_b));
+        }
+    }
+
+    #[test]
+    fn expand_grows_by_rings() {
+        let region = HashSet::from([HexCoord::new(4, -2)]);
+        let once = expand(&region);
+        assert_eq!(7, once.len());
+        let twice = expand(&once);
+        assert_eq!(19, twice.len());
+        assert!(twice.is_superset(&once));
+    }
+
+    #[test]
+    fn region_aabb_covers_every_tile() {
+        let layout = LayoutMode::Hexagonal;
+        assert_eq!(None, region_aabb(layout, 1., &[]));
+        let tiles = HexCoord::origin().range(3);
+        let (min, max) = region_aabb(layout, 2., &tiles).unwrap();
+        for c in &tiles {
+            let p = center_with_layout(layout, 2., c, &[0., 0., 0.]);
+            assert!(min[0] < p[0] && p[0] < max[0]);
+            assert!(min[2] < p[2] && p[2] < max[2]);
+        }
+        // A single tile is as wide as two inner radii, and as deep as two outer radii
+        let (min, max) = region_aabb(layout, 1., &[HexCoord::origin()]).unwrap();
+        assert!((max[0] - min[0] - 2. * HEX_INNER_RADIUS_RATIO).abs() < 1e-6);
+        assert!((max[2] - min[2] - 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shared_border_pairs_touching_edges() {
+        let origin = HexCoord::origin();
+        let a = HashSet::from([origin, origin.south()]);
+        let b = HashSet::from([origin.southeast(), HexCoord::new(10, 10)]);
+        let mut border = shared_border(&a, &b);
+        border.sort_by_key(|(c, _)| (c.q, c.r));
+        // The southeast tile touches the origin on one side, and the tile south of the origin on another
+        assert_eq!(
+            vec![
+                (origin, Direction::Southeast),
+                (origin.south(), Direction::Northeast),
+            ],
+            border
+        );
+        for (c, dir) in border {
+            assert!(b.contains(&c.neighbor(dir)));
+        }
+    }
+
+    #[test]
+    fn hex_at_point_finds_containing_tile() {
+        for c in [
+            HexCoord::origin(),
+            HexCoord::new(3, -2),
+            HexCoord::new(-4, 7),
+            HexCoord::new(-1, -5),
+        ] {
+            for layout in [LayoutMode::Sheared, LayoutMode::Hexagonal] {
+                let p = center_with_layout(layout, 2., &c, &[0., 0., 0.]);
+                assert_eq!(c, hex_at_point(layout, 2., &p));
+                // Anywhere comfortably inside the tile should find it too
+                let nudged = [p[0] + 0.8, p[1] + 5., p[2] - 0.8];
+                assert_eq!(c, hex_at_point(layout, 2., &nudged));
+            }
+        }
+    }
+
+    #[test]
+    fn hex_at_point_round_trips_the_sample_grid() {
+        for q in -15..15 {
+            for r in -15..15 {
+                let c = HexCoord::new(q, r);
+                for (layout, radius) in [
+                    (LayoutMode::Sheared, 1.),
+                    (LayoutMode::Sheared, 2.5),
+                    (LayoutMode::Hexagonal, 1.),
+                ] {
+                    let p = center_with_layout(layout, radius, &c, &[0., 0., 0.]);
+                    assert_eq!(c, hex_at_point(layout, radius, &p));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn selection_ring_hugs_the_tile_edge() {
+        let (mut points, mut normals, mut indices) = (vec![], vec![], vec![]);
+        selection_ring_points(&mut points, 1., 0.1, 0.05, &HexCoord::origin());
+        selection_ring_normals(&mut normals);
+        selection_ring_indices(&mut indices);
+        assert_eq!(points.len(), normals.len());
+        assert!(indices.iter().all(|i| (*i as usize) < points.len()));
+
+        let distances: Vec<_> = points
+            .iter()
+            .map(|[x, _, z]| (x * x + z * z).sqrt())
+            .collect();
+        let outer = distances.iter().cloned().fold(0., f32::max);
+        let inner = distances.iter().cloned().fold(f32::MAX, f32::min);
+        assert!((outer - 1.).abs() < 1e-5);
+        // The inner edge is set in by the width of the band
+        assert!((inner - 0.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn edge_between_neighbors_lies_between_them() {
+        let a = HexCoord::new(1, 0);
+        let b = a.south();
+        let [start, end] = edge_between(LayoutMode::Sheared, 1., &a, &b);
+        let dist = |p: [f32; 3], q: [f32; 3]| {
+            ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2)).sqrt()
+        };
+        assert!((dist(start, end) - 1.).abs() < 1e-5);
+        let (ca, cb) = (center(1., &a, &[0.; 3]), center(1., &b, &[0.; 3]));
+        for p in [start, end] {
+            assert!((dist(p, ca) - dist(p, cb)).abs() < 1e-5);
+            // Both ends sit on corners of each tile
+            assert!((dist(p, ca) - 1.).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn edges_meet_every_neighbor_on_a_hexagonal_layout() {
+        let layout = LayoutMode::Hexagonal;
+        let close = |p: [f32; 3], q: [f32; 3]| (0..3).all(|i| (p[i] - q[i]).abs() < 1e-5);
+        let corners = |c: &HexCoord| {
+            let mut ring = vec![];
+            let middle = center_with_layout(layout, 1., c, &[0., 0., 0.]);
+            flat_hexagon_ring(&mut ring, 1., &HexCoord::origin(), &middle);
+            ring
+        };
+        // An even row away from the origin, where the sheared layout would pull half the neighbors apart
+        let a = HexCoord::new(0, 2);
+        let ca = center_with_layout(layout, 1., &a, &[0., 0., 0.]);
+        for b in a.neighbors() {
+            let cb = center_with_layout(layout, 1., &b, &[0., 0., 0.]);
+            let apart = ((ca[0] - cb[0]).powi(2) + (ca[2] - cb[2]).powi(2)).sqrt();
+            assert!((apart - 2. * HEX_INNER_RADIUS_RATIO).abs() < 1e-5, "{b}");
+            for p in edge_between(layout, 1., &a, &b) {
+                assert!(corners(&a).iter().any(|q| close(p, *q)), "{b}");
+                assert!(corners(&b).iter().any(|q| close(p, *q)), "{b}");
+            }
+        }
+    }
+
+    #[test]
+    fn pointy_top_turns_the_whole_grid() {
+        let c = HexCoord::new(2, -3);
+        let flat = center_with_orientation(Orientation::FlatTop, 1.5, &c, &[0., 1., 0.]);
+        assert_eq!(center(1.5, &c, &[0., 1., 0.]), flat);
+
+        let mut flat_ring = vec![];
+        let mut pointy_ring = vec![];
+        flat_hexagon_ring(&mut flat_ring, 1.5, &c, &[0.; 3]);
+        flat_hexagon_ring_with_orientation(
+            &mut pointy_ring,
+            Orientation::PointyTop,
+            1.5,
+            &c,
+            &[0.; 3],
+        );
+        let pointy = center_with_orientation(Orientation::PointyTop, 1.5, &c, &[0.; 3]);
+        for p in &pointy_ring {
+            let d = ((p[0] - pointy[0]).powi(2) + (p[2] - pointy[2]).powi(2)).sqrt();
+            assert!((d - 1.5).abs() < 1e-5);
+        }
+        // The east corner now points along the x axis rather than the z axis
+        let east = east_corner_with_orientation(Orientation::PointyTop, 1.5, &c, &[0.; 3]);
+        assert!((east[0] - pointy[0]).abs() > 1.4);
+        assert!((east[2] - pointy[2]).abs() < 1e-5);
+
+        // Turning keeps the ring going the same way round, so triangles still face up
+        let winding = |ring: &[[f32; 3]]| {
+            ring.windows(2)
+                .map(|w| w[0][0] * w[1][2] - w[1][0] * w[0][2])
+                .sum::<f32>()
+        };
+        assert_eq!(winding(&flat_ring).signum(), winding(&pointy_ring).signum());
+
+        // Neighbors stay the same distance apart
+        let n = c.north();
+        let dist = |o: Orientation| {
+            let (a, b) = (
+                center_with_orientation(o, 1.5, &c, &[0.; 3]),
+                center_with_orientation(o, 1.5, &n, &[0.; 3]),
+            );
+            ((a[0] - b[0]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+        };
+        assert!((dist(Orientation::FlatTop) - dist(Orientation::PointyTop)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn perimeter_counts_exposed_edges() {
+        let origin = HexCoord::new(-1, 3);
+        assert_eq!(0, perimeter(&HashSet::new()));
+        assert_eq!(6, perimeter(&HashSet::from([origin])));
+        assert_eq!(10, perimeter(&HashSet::from([origin, origin.north()])));
+        let disk: HashSet<_> = origin.range(1).into_iter().collect();
+        assert_eq!(18, perimeter(&disk));
+        // Stringing the same tiles out in a line leaves more of them exposed
+        let line: HashSet<_> = (0..7)
+            .map(|i| HexCoord::new(origin.q + i, origin.r))
+            .collect();
+        assert!(perimeter(&line) > perimeter(&disk));
+    }
+
+    #[test]
+    fn bevel_indices_stay_within_the_tile() {
+        let (mut points, mut normals, mut indices) = (vec![], vec![], vec![]);
+        bevel_hexagon_points(&mut points, 1., DEFAULT_BEVEL_FACTOR, &HexCoord::origin());
+        bevel_hexagon_normals(&mut normals);
+        bevel_hexagon_indices(&mut indices);
+        assert_eq!(points.len(), normals.len());
+        assert_eq!(0, indices.len() % 3);
+        assert!(indices.iter().all(|i| (*i as usize) < points.len()));
+    }
+
+    #[test]
+    fn chunk_mesh_offsets_each_tile() {
+        let layout = LayoutMode::Hexagonal;
+        let a = center_with_layout(
+            layout,
+            DEFAULT_HEX_RADIUS,
+            &HexCoord::new(1, 2),
+            &[0., 0.5, 0.],
+        );
+        let b = center_with_layout(
+            layout,
+            DEFAULT_HEX_RADIUS,
+            &HexCoord::new(-3, 0),
+            &[0., 2., 0.],
+        );
+        let (red, blue) = ([1., 0., 0., 1.], [0., 0., 1., 1.]);
+        let single = build_chunk_mesh(&[(a, red)]);
+        let chunk = build_chunk_mesh(&[(a, red), (b, blue)]);
+        let per_tile = single.positions.len();
+        assert_eq!(2 * per_tile, chunk.positions.len());
+        assert_eq!(chunk.positions.len(), chunk.normals.len());
+        assert_eq!(chunk.positions.len(), chunk.uvs.len());
+        assert_eq!(chunk.positions.len(), chunk.colors.len());
+        assert!(chunk.colors[..per_tile].iter().all(|c| *c == red));
+        assert!(chunk.colors[per_tile..].iter().all(|c| *c == blue));
+
+        // The second tile's triangles are the first tile's, moved along to its own points
+        let (first, second) = chunk.indices.split_at(single.indices.len());
+        assert_eq!(single.indices, first);
+        for (i, j) in first.iter().zip(second) {
+            assert_eq!(i + per_tile as u32, *j);
+        }
+
+        // And its points are the first tile's, moved over to its own position, starting from the middle of its top
+        assert_eq!(a, chunk.positions[0]);
+        assert_eq!(b, chunk.positions[per_tile]);
+        let shift = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        for (p, q) in chunk.positions[..per_tile]
+            .iter()
+            .zip(&chunk.positions[per_tile..])
+        {
+            for axis in 0..3 {
+                assert!((p[axis] + shift[axis] - q[axis]).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn skirt_reaches_the_requested_depth() {
+        let c = HexCoord::new(2, -1);
+        for depth in [0.5, 3., DEFAULT_SKIRT_DEPTH] {
+            let mut points = vec![];
+            bevel_hexagon_points_with_skirt(&mut points, 1., DEFAULT_BEVEL_FACTOR, depth, &c);
+            let top = points[0][1];
+            let lowest = points.iter().map(|p| p[1]).fold(f32::INFINITY, f32::min);
+            assert!((top - depth - lowest).abs() < 1e-5);
+
+            // The normals don't depend on the depth, and still line up with the points
+            let mut normals = vec![];
+            bevel_hexagon_normals(&mut normals);
+            assert_eq!(points.len(), normals.len());
+        }
+    }
+
+    #[test]
+    fn bevel_uvs_line_up_with_points() {
+        let (mut points, mut uvs) = (vec![], vec![]);
+        bevel_hexagon_points(&mut points, 1., DEFAULT_BEVEL_FACTOR, &HexCoord::origin());
+        bevel_hexagon_uvs(&mut uvs);
+        assert_eq!(points.len(), uvs.len());
+        assert!(uvs
+            .iter()
+            .flatten()
+            .all(|x| (-1e-6..=1. + 1e-6).contains(x)));
+
+        // The top face's corners sit on the inscribed circle, in the same directions as the points themselves
+        let [cx, _, cz] = points[0];
+        for i in 1..8 {
+            let [u, v] = uvs[i];
+            let (du, dv) = (u - 0.5, 0.5 - v);
+            assert!(((du * du + dv * dv).sqrt() - 0.5).abs() < 1e-5);
+            let (dz, dx) = (points[i][2] - cz, points[i][0] - cx);
+            // East is along z and north along x, which the texture has running right and up
+            assert!((du.atan2(dv) - dz.atan2(dx)).abs() < 1e-4);
+        }
+        // And the sides wrap all the way around
+        assert_eq!([0., 0.], uvs[8]);
+        assert_eq!([1., 0.], uvs[14]);
+        assert_eq!([1., 1.], uvs[21]);
+    }
+
+    #[test]
+    fn flat_normals_face_out_of_each_face() {
+        let c = HexCoord::origin();
+        let (mut points, mut normals) = (vec![], vec![]);
+        bevel_hexagon_unshared_points(&mut points, 1., DEFAULT_BEVEL_FACTOR, &c);
+        bevel_hexagon_flat_normals(&mut normals, &points);
+        assert_eq!(points.len(), normals.len());
+        let mut idx = vec![];
+        bevel_hexagon_indices(&mut idx);
+        assert_eq!(idx.len(), points.len());
+        for n in &normals {
+            assert!(((n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt() - 1.).abs() < 1e-5);
+        }
+
+        // Six top triangles, then six slope quads, then six skirt quads
+        let faces: Vec<_> = normals.chunks_exact(3).map(|n| n[0]).collect();
+        assert!(faces[..6].iter().all(|n| (n[1] - 1.).abs() < 1e-5));
+        // The slopes drop as far as they reach out at the corners, so they're about 45 degrees; a little steeper in
+        // the middle of each edge, where they're pulled in to the inner radius
+        let (run, rise) = (HEX_INNER_RADIUS_RATIO, 1.);
+        let slope = run / (run * run + rise * rise).sqrt();
+        assert!(faces[6..18].iter().all(|n| (n[1] - slope).abs() < 1e-4));
+        assert!(faces[18..].iter().all(|n| n[1].abs() < 1e-5));
+
+        // And each points away from the middle of the tile
+        let middle = center(1., &c, &[0., 0., 0.]);
+        for (triangle, n) in points.chunks_exact(3).zip(&faces) {
+            let p = triangle[0];
+            let out = [p[0] - middle[0], p[1] - middle[1] + 1., p[2] - middle[2]];
+            assert!(out[0] * n[0] + out[1] * n[1] + out[2] * n[2] > 0.);
+        }
+    }
+}
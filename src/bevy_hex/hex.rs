@@ -1,6 +1,11 @@
 /// A coordinate on a hex grid, representing distances along the various directions of travel
 /// Invariant: In order to represent a valid hex coordinate, q + r + s must equal 0
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "UncheckedHexCoord")
+)]
 pub struct HexCoord {
     pub q: isize,
     pub r: isize,
@@ -9,6 +14,7 @@ pub struct HexCoord {
 
 /// The directions you can move on a hex grid
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     None,
     North,
@@ -34,6 +40,41 @@ impl Direction {
             Southeast => Northwest,
         }
     }
+
+    /// The direction to step from `from` to reach `to`, or [Direction::None] if they aren't neighbors
+    #[must_use]
+    pub fn between(from: &HexCoord, to: &HexCoord) -> Self {
+        Self::from_coords(from, to).unwrap_or(Direction::None)
+    }
+
+    /// The direction to step from `from` to reach `to`, if they're neighbors.
+    /// A tile isn't its own neighbor, so this is `None` when they're the same; it never returns [Direction::None]
+    #[must_use]
+    pub fn from_coords(from: &HexCoord, to: &HexCoord) -> Option<Self> {
+        DIRECTIONS
+            .iter()
+            .copied()
+            .find(|dir| from.neighbor(*dir) == *to)
+    }
+
+    /// How many sixths of a turn it takes to face `other` after facing this way; positive is clockwise.
+    /// Always takes the shorter way round, from -2 to 3, so turning about is three clockwise turns.
+    /// [Direction::None] has no facing, so turning to or from it takes no turns at all
+    #[must_use]
+    pub fn rotation_to(self, other: Direction) -> isize {
+        let index = |dir| DIRECTIONS.iter().position(|d| *d == dir);
+        match (index(self), index(other)) {
+            (Some(from), Some(to)) => {
+                let turns = (to as isize - from as isize).rem_euclid(6);
+                if turns > 3 {
+                    turns - 6
+                } else {
+                    turns
+                }
+            }
+            _ => 0,
+        }
+    }
 }
 
 impl HexCoord {
@@ -43,12 +84,56 @@ impl HexCoord {
         HexCoord { q, r, s: -q - r }
     }
 
+    /// Build a coordinate from all three of `[q, r, s]`, refusing any that don't add up to 0.
+    /// Components so large that their sum overflows are refused too
+    pub fn from_components([q, r, s]: [isize; 3]) -> Result<Self, InvalidHexCoord> {
+        if q.checked_add(r).and_then(|qr| qr.checked_add(s)) == Some(0) {
+            Ok(HexCoord { q, r, s })
+        } else {
+            Err(InvalidHexCoord([q, r, s]))
+        }
+    }
+
+    /// The three components as `[q, r, s]`, for code that treats every axis the same way
+    #[must_use]
+    pub fn components(&self) -> [isize; 3] {
+        [self.q, self.r, self.s]
+    }
+
     /// The origin of an infinite hex grid
     #[must_use]
     pub fn origin() -> Self {
         HexCoord { q: 0, r: 0, s: 0 }
     }
 
+    /// Round a fractional axial coordinate, like one from picking a point in world space, to the tile containing it.
+    /// Whichever component rounding moved the furthest is recomputed from the other two; if `q` and `r` moved equally,
+    /// `r` is the one recomputed
+    #[must_use]
+    pub fn round(qf: f32, rf: f32) -> Self {
+        cube_round(qf, rf, -qf - rf)
+    }
+
+    /// Snap a possibly slightly invalid fractional coordinate, like one from hand authored data, to the nearest valid tile.
+    /// Any drift away from `q + r + s == 0` is spread evenly across the components before rounding
+    #[must_use]
+    pub fn snap(q: f32, r: f32, s: f32) -> Self {
+        let drift = (q + r + s) / 3.;
+        cube_round(q - drift, r - drift, s - drift)
+    }
+
+    /// This coordinate as seen from `anchor`, as if `anchor` were the origin; the inverse of [HexCoord::absolute_from]
+    #[must_use]
+    pub fn relative_to(&self, anchor: &HexCoord) -> Self {
+        Self::new(self.q - anchor.q, self.r - anchor.r)
+    }
+
+    /// Place a coordinate that was relative to the origin relative to `anchor` instead, like stamping a pattern down
+    #[must_use]
+    pub fn absolute_from(&self, anchor: &HexCoord) -> Self {
+        Self::new(self.q + anchor.q, self.r + anchor.r)
+    }
+
     /// The coordinate to the north
     #[must_use]
     pub fn north(&self) -> Self {
@@ -100,6 +185,169 @@ impl HexCoord {
         }
     }
 
+    /// The number of steps it takes to walk from this coordinate to `other`.
+    /// The math is done in `i128` so coordinates near the ends of `isize` can't overflow,
+    /// and the rare distance too big for a `usize` comes back as `usize::MAX`
+    #[must_use]
+    pub fn distance(&self, other: &HexCoord) -> usize {
+        let diff = |a: isize, b: isize| (a as i128 - b as i128).unsigned_abs();
+        let steps = (diff(self.q, other.q) + diff(self.r, other.r) + diff(self.s, other.s)) / 2;
+        usize::try_from(steps).unwrap_or(usize::MAX)
+    }
+
+    /// Every tile along the straight line from this coordinate to `other`, including both ends, one step at a time
+    #[must_use]
+    pub fn line_to(&self, other: &HexCoord) -> Vec<HexCoord> {
+        let steps = self.distance(other);
+        if steps == 0 {
+            return vec![*self];
+        }
+        (0..=steps)
+            .map(|i| {
+                let t = i as f32 / steps as f32;
+                let lerp = |a: isize, b: isize| a as f32 + (b - a) as f32 * t;
+                // Nudge the line off of tile edges, so it picks the same side consistently
+                cube_round(
+                    lerp(self.q, other.q) + 1e-6,
+                    lerp(self.r, other.r) + 2e-6,
+                    lerp(self.s, other.s) - 3e-6,
+                )
+            })
+            .collect()
+    }
+
+    /// All coordinates within `n` steps of this one, including itself; there are `3n² + 3n + 1` of them
+    #[must_use]
+    pub fn range(&self, n: usize) -> Vec<HexCoord> {
+        let n = n as isize;
+        let mut results = vec![];
+        for dq in -n..=n {
+            for dr in (-n).max(-dq - n)..=n.min(-dq + n) {
+                results.push(HexCoord::new(self.q + dq, self.r + dr));
+            }
+        }
+        results
+    }
+
+    /// All coordinates exactly `radius` steps away from this one, walking clockwise around the ring
+    #[must_use]
+    pub fn ring(&self, radius: usize) -> Vec<HexCoord> {
+        if radius == 0 {
+            return vec![*self];
+        }
+        let mut results = Vec::with_capacity(6 * radius);
+        // Start at the southwest corner, so the first edge we walk is the one heading north
+        let mut current = *self;
+        for _ in 0..radius {
+            current = current.southwest();
+        }
+        for dir in DIRECTIONS {
+            for _ in 0..radius {
+                results.push(current);
+                current = current.neighbor(*dir);
+            }
+        }
+        results
+    }
+
+    /// All coordinates at least `inner` and at most `outer` steps away from this one, working outward ring by ring
+    #[must_use]
+    pub fn annulus(&self, inner: usize, outer: usize) -> Vec<HexCoord> {
+        (inner..=outer)
+            .flat_map(|radius| self.ring(radius))
+            .collect()
+    }
+
+    /// All coordinates within `radius` steps, starting with this one and spiralling outward ring by ring.
+    /// Holds the same tiles as [HexCoord::range], in an order that's handy for popping things in from the middle
+    #[must_use]
+    pub fn spiral(&self, radius: usize) -> Vec<HexCoord> {
+        self.annulus(0, radius)
+    }
+
+    /// The coordinates within `radius` steps, grouped into rings and paired with how far out each ring is.
+    /// Handy for effects that spread outward, like a shockwave setting off each ring a little after the last
+    #[must_use]
+    pub fn rings(&self, radius: usize) -> Vec<(usize, Vec<HexCoord>)> {
+        (0..=radius).map(|k| (k, self.ring(k))).collect()
+    }
+
+    /// Turn this coordinate around `center` by `steps` sixths of a turn, clockwise for positive steps.
+    /// Six steps either way comes back round to where it started, and three is the opposite side of `center`
+    #[must_use]
+    pub fn rotate_around(&self, center: &HexCoord, steps: i32) -> Self {
+        let mut relative = self.relative_to(center);
+        for _ in 0..steps.rem_euclid(6) {
+            relative = HexCoord {
+                q: -relative.r,
+                r: -relative.s,
+                s: -relative.q,
+            };
+        }
+        relative.absolute_from(center)
+    }
+
+    /// Mirror this coordinate across the q axis through the origin, keeping q and swapping r and s
+    #[must_use]
+    pub fn reflect_q(&self) -> Self {
+        HexCoord {
+            q: self.q,
+            r: self.s,
+            s: self.r,
+        }
+    }
+
+    /// Mirror this coordinate across the r axis through the origin, keeping r and swapping q and s
+    #[must_use]
+    pub fn reflect_r(&self) -> Self {
+        HexCoord {
+            q: self.s,
+            r: self.r,
+            s: self.q,
+        }
+    }
+
+    /// Mirror this coordinate across the s axis through the origin, keeping s and swapping q and r
+    #[must_use]
+    pub fn reflect_s(&self) -> Self {
+        HexCoord {
+            q: self.r,
+            r: self.q,
+            s: self.s,
+        }
+    }
+
+    /// Convert to "odd-r" offset coordinates, returning `(col, row)`, where odd rows are shoved half a tile over.
+    /// Rows are just `r`; see <https://www.redblobgames.com/grids/hexagons/#conversions-offset>.
+    /// Odd rows always shove right, so a rectangle of offsets is a rectangle of tiles with the `Hexagonal` layout.
+    /// The default `Sheared` layout isn't quite the same: its columns are just `q`, and negative odd rows shove left
+    #[must_use]
+    pub fn to_offset(&self) -> (isize, isize) {
+        let col = self.q + (self.r - (self.r & 1)) / 2;
+        (col, self.r)
+    }
+
+    /// Convert from "odd-r" offset coordinates, the inverse of [HexCoord::to_offset]
+    #[must_use]
+    pub fn from_offset(col: isize, row: isize) -> Self {
+        HexCoord::new(col - (row - (row & 1)) / 2, row)
+    }
+
+    /// Convert to "doubled-width" coordinates, returning `(col, row)`. Rows are `r`, like [HexCoord::to_offset], but
+    /// columns count half tiles, so each tile sits two columns from the next in its row and `col + row` is always even.
+    /// See <https://www.redblobgames.com/grids/hexagons/#coordinates-doubled>
+    #[must_use]
+    pub fn to_doubled(&self) -> (isize, isize) {
+        (2 * self.q + self.r, self.r)
+    }
+
+    /// Convert from "doubled-width" coordinates, the inverse of [HexCoord::to_doubled].
+    /// Only pairs where `col + row` is even are tiles; for the rest, this picks the tile half a column to the left
+    #[must_use]
+    pub fn from_doubled(col: isize, row: isize) -> Self {
+        HexCoord::new((col - row).div_euclid(2), row)
+    }
+
     /// Yield the neighbor coordinates, starting from North and going clockwise
     pub fn neighbors(&self) -> impl Iterator<Item = HexCoord> + '_ {
         struct NeighborIter<'a> {
@@ -117,6 +365,127 @@ impl HexCoord {
             iter: DIRECTIONS.iter(),
         }
     }
+
+    /// Yield the neighbor coordinates other than the one in direction `skip`, starting from North and going clockwise.
+    /// Handy for walking without doubling straight back the way you came
+    pub fn neighbors_except(&self, skip: Direction) -> impl Iterator<Item = HexCoord> + '_ {
+        DIRECTIONS
+            .iter()
+            .filter(move |dir| **dir != skip)
+            .map(move |dir| self.neighbor(*dir))
+    }
+
+    /// The six neighbors, ordered from lowest to highest `key`; ties keep their clockwise order from North
+    pub fn neighbors_sorted_by<K: Ord>(&self, key: impl Fn(&HexCoord) -> K) -> Vec<HexCoord> {
+        let mut neighbors: Vec<_> = self.neighbors().collect();
+        neighbors.sort_by_key(|c| key(c));
+        neighbors
+    }
+
+    /// The six corners of this tile, starting from the northern point and going clockwise
+    #[must_use]
+    pub fn dual(&self) -> [HexVertex; 6] {
+        use Corner::*;
+        [
+            HexVertex::new(*self, Top),
+            HexVertex::new(self.northeast(), Bottom),
+            HexVertex::new(self.south(), Top),
+            HexVertex::new(*self, Bottom),
+            HexVertex::new(self.southwest(), Top),
+            HexVertex::new(self.north(), Bottom),
+        ]
+    }
+}
+
+/// Round a fractional cube coordinate to the nearest tile.
+/// Rounding each component independently can break the invariant, so we recompute whichever one moved the most
+fn cube_round(qf: f32, rf: f32, sf: f32) -> HexCoord {
+    let (q, r, s) = (qf.round(), rf.round(), sf.round());
+    let (dq, dr, ds) = ((q - qf).abs(), (r - rf).abs(), (s - sf).abs());
+    let (q, r) = if dq > dr && dq > ds {
+        (-r - s, r)
+    } else if dr > ds {
+        (q, -q - s)
+    } else {
+        // s is recomputed from the other two anyway
+        (q, r)
+    };
+    HexCoord::new(q as isize, r as isize)
+}
+
+impl std::fmt::Display for HexCoord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {}, {})", self.q, self.r, self.s)
+    }
+}
+
+impl From<(isize, isize)> for HexCoord {
+    /// Construct a hex coordinate from a `(q, r)` pair, enforcing the invariant on `s`
+    fn from((q, r): (isize, isize)) -> Self {
+        HexCoord::new(q, r)
+    }
+}
+
+impl From<HexCoord> for (isize, isize) {
+    /// Flatten a hex coordinate into a `(q, r)` pair; `s` can always be recovered from the other two
+    fn from(c: HexCoord) -> Self {
+        (c.q, c.r)
+    }
+}
+
+#[cfg(feature = "hexx")]
+impl From<hexx::Hex> for HexCoord {
+    /// hexx stores axial coordinates as `(x, y)`, which are our `q` and `r`
+    fn from(h: hexx::Hex) -> Self {
+        Self::new(h.x as isize, h.y as isize)
+    }
+}
+
+#[cfg(feature = "hexx")]
+impl TryFrom<HexCoord> for hexx::Hex {
+    type Error = std::num::TryFromIntError;
+
+    /// hexx only has room for `i32` components, so tiles further out than that can't be converted.
+    /// `s` is checked too, since hexx works it out from the other two
+    fn try_from(c: HexCoord) -> Result<Self, Self::Error> {
+        i32::try_from(c.s)?;
+        Ok(hexx::Hex::new(i32::try_from(c.q)?, i32::try_from(c.r)?))
+    }
+}
+
+/// Three components that can't be a [HexCoord], because they don't add up to 0
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InvalidHexCoord(pub [isize; 3]);
+
+impl std::fmt::Display for InvalidHexCoord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [q, r, s] = self.0;
+        write!(
+            f,
+            "invalid hex coordinate ({q}, {r}, {s}): q + r + s must equal 0"
+        )
+    }
+}
+
+impl std::error::Error for InvalidHexCoord {}
+
+/// A coordinate straight out of a file, before we've checked it keeps the invariant
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedHexCoord {
+    q: isize,
+    r: isize,
+    s: isize,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedHexCoord> for HexCoord {
+    type Error = InvalidHexCoord;
+
+    /// Refuse coordinates that break the invariant, rather than quietly building a tile that can't exist
+    fn try_from(UncheckedHexCoord { q, r, s }: UncheckedHexCoord) -> Result<Self, Self::Error> {
+        HexCoord::from_components([q, r, s])
+    }
 }
 
 /// All directions, for convenient enumeration
@@ -129,6 +498,54 @@ pub const DIRECTIONS: &[Direction] = &[
     Direction::Northwest,
 ];
 
+/// Which of a tile's two pointed ends a vertex sits on
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Corner {
+    Top,
+    Bottom,
+}
+
+/// A corner where three tiles meet.
+/// Every corner is either the northern (top) or southern (bottom) point of exactly one tile, so that names it uniquely
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HexVertex {
+    pub hex: HexCoord,
+    pub corner: Corner,
+}
+
+impl HexVertex {
+    pub fn new(hex: HexCoord, corner: Corner) -> Self {
+        Self { hex, corner }
+    }
+
+    /// The three tiles that meet at this corner, starting with the one it's named after
+    #[must_use]
+    pub fn hexes(&self) -> [HexCoord; 3] {
+        match self.corner {
+            Corner::Top => [self.hex, self.hex.north(), self.hex.northeast()],
+            Corner::Bottom => [self.hex, self.hex.south(), self.hex.southwest()],
+        }
+    }
+
+    /// The corner where three tiles meet, in any order, or `None` if they don't all touch each other
+    #[must_use]
+    pub fn from_hexes(hexes: [HexCoord; 3]) -> Option<Self> {
+        // Two of the tiles share a row, and the corner is the point of the third that pokes between them
+        let lone = hexes
+            .iter()
+            .find(|h| hexes.iter().filter(|other| other.r == h.r).count() == 1)?;
+        let corner = if hexes.iter().any(|h| h.r < lone.r) {
+            Corner::Top
+        } else {
+            Corner::Bottom
+        };
+        let vertex = Self::new(*lone, corner);
+        let expected = vertex.hexes();
+        // Checking this way round also catches the same tile being passed twice
+        expected.iter().all(|h| hexes.contains(h)).then_some(vertex)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,4 +572,429 @@ mod tests {
             assert_eq!(point, neighbor.neighbor(dir.opposite()));
         }
     }
+
+    #[test]
+    fn tuple_round_trip() {
+        let c: HexCoord = (3, -7).into();
+        assert_eq!(HexCoord::new(3, -7), c);
+        assert_eq!(0, c.q + c.r + c.s);
+        let t: (isize, isize) = c.into();
+        assert_eq!((3, -7), t);
+    }
+
+    #[test]
+    fn snap_normalizes_near_valid_triples() {
+        assert_eq!(HexCoord::new(2, -1), HexCoord::snap(2., -1., -1.));
+        assert_eq!(HexCoord::new(2, -1), HexCoord::snap(2.05, -0.98, -1.01));
+        assert_eq!(HexCoord::new(-3, 5), HexCoord::snap(-3.1, 5.2, -2.));
+        let snapped = HexCoord::snap(0.51, 0.49, -0.97);
+        assert_eq!(0, snapped.q + snapped.r + snapped.s);
+    }
+
+    #[test]
+    fn display_shows_all_components() {
+        assert_eq!("(2, -5, 3)", HexCoord::new(2, -5).to_string());
+    }
+
+    #[test]
+    fn annulus_covers_rings_between_bounds() {
+        let center = HexCoord::new(-2, 4);
+        let annulus = center.annulus(1, 2);
+        assert_eq!(18, annulus.len());
+        assert!(!annulus.contains(&center));
+        assert!(annulus
+            .iter()
+            .all(|c| (1..=2).contains(&c.distance(&center))));
+        assert_eq!(vec![center], center.annulus(0, 0));
+        assert!(center.annulus(3, 2).is_empty());
+    }
+
+    #[test]
+    fn neighbors_sorted_by_distance_to_goal() {
+        let start = HexCoord::new(1, 1);
+        let goal = HexCoord::new(5, -3);
+        let sorted = start.neighbors_sorted_by(|c| c.distance(&goal));
+        assert_eq!(6, sorted.len());
+        assert_eq!(start.northeast(), sorted[0]);
+        assert_eq!(start.distance(&goal) - 1, sorted[0].distance(&goal));
+        assert!(sorted
+            .windows(2)
+            .all(|w| w[0].distance(&goal) <= w[1].distance(&goal)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        for c in HexCoord::new(-3, 8).range(2) {
+            let json = serde_json::to_string(&c).unwrap();
+            assert_eq!(c, serde_json::from_str(&json).unwrap());
+        }
+        for dir in DIRECTIONS.iter().chain([&Direction::None]) {
+            let json = serde_json::to_string(dir).unwrap();
+            assert_eq!(*dir, serde_json::from_str::<Direction>(&json).unwrap());
+        }
+        assert_eq!(
+            HexCoord::new(1, -3),
+            serde_json::from_str(r#"{"q": 1, "r": -3, "s": 2}"#).unwrap()
+        );
+
+        let error = serde_json::from_str::<HexCoord>(r#"{"q": 1, "r": 1, "s": 1}"#).unwrap_err();
+        assert!(error.to_string().contains("q + r + s must equal 0"));
+
+        // Components that only add up to 0 by wrapping around are refused too
+        let extreme = format!(r#"{{"q": {0}, "r": {0}, "s": 2}}"#, isize::MAX);
+        let error = serde_json::from_str::<HexCoord>(&extreme).unwrap_err();
+        assert!(error.to_string().contains("q + r + s must equal 0"));
+        let c = HexCoord::new(isize::MAX, isize::MIN + 1);
+        let json = serde_json::to_string(&c).unwrap();
+        assert_eq!(c, serde_json::from_str(&json).unwrap());
+    }
+
+    #[cfg(feature = "hexx")]
+    #[test]
+    fn hexx_round_trip() {
+        for c in HexCoord::new(3, -1).range(3) {
+            let h = hexx::Hex::try_from(c).unwrap();
+            assert_eq!(
+                (c.q, c.r, c.s),
+                (h.x as isize, h.y as isize, h.z() as isize)
+            );
+            assert_eq!(c, HexCoord::from(h));
+        }
+        assert_eq!(
+            HexCoord::origin().north(),
+            HexCoord::from(hexx::Hex::ZERO + hexx::Hex::new(0, -1))
+        );
+
+        // Tiles too far out for hexx's i32 components don't convert
+        assert!(hexx::Hex::try_from(HexCoord::new(i32::MAX as isize + 1, 0)).is_err());
+        assert!(hexx::Hex::try_from(HexCoord::new(0, i32::MIN as isize - 1)).is_err());
+        assert!(hexx::Hex::try_from(HexCoord::new(i32::MAX as isize, i32::MAX as isize)).is_err());
+        let edge = HexCoord::new(i32::MAX as isize, i32::MIN as isize);
+        assert_eq!(edge, HexCoord::from(hexx::Hex::try_from(edge).unwrap()));
+    }
+
+    #[test]
+    fn vertices_join_three_adjacent_tiles() {
+        let center = HexCoord::new(2, -3);
+        let corners = center.dual();
+        for (i, vertex) in corners.iter().enumerate() {
+            let hexes = vertex.hexes();
+            assert!(hexes.contains(&center));
+            for a in hexes {
+                for b in hexes.iter().filter(|b| **b != a) {
+                    assert_eq!(1, a.distance(b));
+                }
+            }
+            // Each corner is shared with the neighbors either side of it
+            assert!(hexes.contains(&center.neighbor(DIRECTIONS[i])));
+            assert!(hexes.contains(&center.neighbor(DIRECTIONS[(i + 1) % 6])));
+
+            let [a, b, c] = hexes;
+            assert_eq!(Some(*vertex), HexVertex::from_hexes([c, a, b]));
+            assert_eq!(Some(*vertex), HexVertex::from_hexes([b, c, a]));
+        }
+        let far = HexCoord::new(9, 9);
+        assert_eq!(None, HexVertex::from_hexes([center, center.north(), far]));
+        assert_eq!(
+            None,
+            HexVertex::from_hexes([center, center.north(), center.south()])
+        );
+        assert_eq!(
+            None,
+            HexVertex::from_hexes([center.north(), center.north(), center])
+        );
+    }
+
+    #[test]
+    fn neighbors_except_skips_one_direction() {
+        let point = HexCoord::new(4, -2);
+        for dir in DIRECTIONS {
+            let neighbors: Vec<_> = point.neighbors_except(*dir).collect();
+            assert_eq!(5, neighbors.len());
+            assert!(!neighbors.contains(&point.neighbor(*dir)));
+            assert!(neighbors.iter().all(|n| n.distance(&point) == 1));
+        }
+        assert_eq!(6, point.neighbors_except(Direction::None).count());
+    }
+
+    #[test]
+    fn relative_round_trip() {
+        let anchor = HexCoord::new(-4, 7);
+        for c in HexCoord::new(2, 1).range(3) {
+            let relative = c.relative_to(&anchor);
+            assert_eq!(0, relative.q + relative.r + relative.s);
+            assert_eq!(c, relative.absolute_from(&anchor));
+            assert_eq!(c.distance(&anchor), relative.distance(&HexCoord::origin()));
+        }
+        assert_eq!(HexCoord::origin(), anchor.relative_to(&anchor));
+        assert_eq!(
+            anchor.north(),
+            HexCoord::origin().north().absolute_from(&anchor)
+        );
+    }
+
+    #[test]
+    fn distance_to_neighbors_and_self() {
+        let point = HexCoord::new(-6, 11);
+        assert_eq!(0, point.distance(&point));
+        for dir in DIRECTIONS {
+            assert_eq!(1, point.distance(&point.neighbor(*dir)));
+        }
+    }
+
+    #[test]
+    fn distance_is_walkable() {
+        let mut rand = rand::thread_rng();
+        for _ in 0..20 {
+            let from = HexCoord::new(rand.gen_range(-50..50), rand.gen_range(-50..50));
+            let to = HexCoord::new(rand.gen_range(-50..50), rand.gen_range(-50..50));
+            let steps = from.distance(&to);
+            assert_eq!(steps, to.distance(&from));
+            // Always stepping to whichever neighbor is closest gets there in exactly that many steps
+            let mut current = from;
+            for _ in 0..steps {
+                current = current.neighbors_sorted_by(|c| c.distance(&to))[0];
+            }
+            assert_eq!(to, current);
+        }
+    }
+
+    #[test]
+    fn distance_does_not_overflow() {
+        let far_east = HexCoord {
+            q: isize::MAX,
+            r: 0,
+            s: -isize::MAX,
+        };
+        let far_west = HexCoord {
+            q: -isize::MAX,
+            r: 0,
+            s: isize::MAX,
+        };
+        assert_eq!(isize::MAX as usize * 2, far_east.distance(&far_west));
+        assert_eq!(0, far_east.distance(&far_east));
+        assert_eq!(isize::MAX as usize, far_east.distance(&HexCoord::origin()));
+    }
+
+    #[test]
+    fn line_steps_between_neighbors() {
+        let mut rand = rand::thread_rng();
+        for _ in 0..20 {
+            let from = HexCoord::new(rand.gen_range(-20..20), rand.gen_range(-20..20));
+            let to = HexCoord::new(rand.gen_range(-20..20), rand.gen_range(-20..20));
+            let line = from.line_to(&to);
+            assert_eq!(from.distance(&to) + 1, line.len());
+            assert_eq!(Some(&from), line.first());
+            assert_eq!(Some(&to), line.last());
+            for pair in line.windows(2) {
+                assert_eq!(1, pair[0].distance(&pair[1]));
+            }
+        }
+        let origin = HexCoord::origin();
+        assert_eq!(vec![origin], origin.line_to(&origin));
+        let north = origin.north().north();
+        assert_eq!(vec![origin, origin.north(), north], origin.line_to(&north));
+    }
+
+    #[test]
+    fn round_keeps_whole_coordinates_and_breaks_ties() {
+        for c in HexCoord::origin().range(6) {
+            assert_eq!(c, HexCoord::round(c.q as f32, c.r as f32));
+        }
+        // q and r are both 0.4 off, further than s, and r gets recomputed
+        assert_eq!(HexCoord::new(0, 1), HexCoord::round(0.4, 0.4));
+        assert_eq!(HexCoord::new(1, -1), HexCoord::round(0.9, -0.6));
+        assert_eq!(HexCoord::new(-2, 3), HexCoord::round(-2.2, 3.1));
+        let rounded = HexCoord::round(0.5, 0.5);
+        assert_eq!(0, rounded.q + rounded.r + rounded.s);
+    }
+
+    #[test]
+    fn range_counts_every_tile_within_reach() {
+        let center = HexCoord::new(4, -7);
+        assert_eq!(vec![center], center.range(0));
+        for n in 0..6 {
+            let tiles = center.range(n);
+            assert_eq!(3 * n * n + 3 * n + 1, tiles.len());
+            let unique: std::collections::HashSet<_> = tiles.iter().collect();
+            assert_eq!(tiles.len(), unique.len());
+            for c in &tiles {
+                assert_eq!(0, c.q + c.r + c.s);
+                assert!(center.distance(c) <= n);
+            }
+        }
+    }
+
+    #[test]
+    fn ring_holds_each_tile_at_the_radius_once() {
+        let center = HexCoord::new(-2, 5);
+        assert_eq!(vec![center], center.ring(0));
+        for radius in 1..6 {
+            let tiles = center.ring(radius);
+            assert_eq!(6 * radius, tiles.len());
+            let unique: std::collections::HashSet<_> = tiles.iter().collect();
+            assert_eq!(tiles.len(), unique.len());
+            assert!(tiles.iter().all(|c| center.distance(c) == radius));
+            // Consecutive tiles are neighbors, all the way round
+            for (a, b) in tiles.iter().zip(tiles.iter().cycle().skip(1)) {
+                assert_eq!(1, a.distance(b));
+            }
+        }
+    }
+
+    #[test]
+    fn spiral_works_outward() {
+        let center = HexCoord::new(3, 1);
+        for radius in 0..5 {
+            let tiles = center.spiral(radius);
+            assert_eq!(center, tiles[0]);
+            assert_eq!(3 * radius * radius + 3 * radius + 1, tiles.len());
+            let distances: Vec<_> = tiles.iter().map(|c| center.distance(c)).collect();
+            assert!(distances.windows(2).all(|w| w[0] <= w[1]));
+            let mut sorted = tiles.clone();
+            let mut range = center.range(radius);
+            sorted.sort_by_key(|c| (c.q, c.r));
+            range.sort_by_key(|c| (c.q, c.r));
+            assert_eq!(range, sorted);
+        }
+    }
+
+    #[test]
+    fn offset_round_trip() {
+        for row in -7..=7 {
+            for col in -7..=7 {
+                let c = HexCoord::from_offset(col, row);
+                assert_eq!(0, c.q + c.r + c.s);
+                assert_eq!((col, row), c.to_offset());
+            }
+        }
+        for c in HexCoord::new(-4, 9).range(5) {
+            let (col, row) = c.to_offset();
+            assert_eq!(c, HexCoord::from_offset(col, row));
+        }
+        // An odd row sits half a tile right of the row above, so both tiles above it are neighbors
+        let c = HexCoord::from_offset(2, 1);
+        assert_eq!(1, c.distance(&HexCoord::from_offset(2, 0)));
+        assert_eq!(1, c.distance(&HexCoord::from_offset(3, 0)));
+    }
+
+    #[test]
+    fn doubled_round_trip() {
+        for c in HexCoord::new(6, -3).range(6) {
+            let (col, row) = c.to_doubled();
+            assert_eq!(0, (col + row).rem_euclid(2));
+            assert_eq!(c, HexCoord::from_doubled(col, row));
+        }
+        // Neighbors in the same row are two columns apart, and diagonal neighbors one
+        let (col, row) = HexCoord::origin().to_doubled();
+        assert_eq!((col + 2, row), HexCoord::origin().southeast().to_doubled());
+        assert_eq!(
+            (col + 1, row - 1),
+            HexCoord::origin().northeast().to_doubled()
+        );
+    }
+
+    #[test]
+    fn rotating_a_neighbor_visits_each_direction() {
+        let center = HexCoord::origin();
+        let visited: Vec<_> = (0..6)
+            .map(|steps| center.north().rotate_around(&center, steps))
+            .collect();
+        let neighbors: Vec<_> = center.neighbors().collect();
+        assert_eq!(neighbors, visited);
+
+        let pivot = HexCoord::new(3, -5);
+        for c in pivot.range(3) {
+            assert_eq!(c, c.rotate_around(&pivot, 6));
+            assert_eq!(c, c.rotate_around(&pivot, -6));
+            assert_eq!(c, c.rotate_around(&pivot, 2).rotate_around(&pivot, -2));
+            let opposite = HexCoord::new(2 * pivot.q - c.q, 2 * pivot.r - c.r);
+            assert_eq!(opposite, c.rotate_around(&pivot, 3));
+            assert_eq!(
+                pivot.distance(&c),
+                pivot.distance(&c.rotate_around(&pivot, 1))
+            );
+        }
+    }
+
+    #[test]
+    fn reflections_undo_themselves() {
+        let origin = HexCoord::origin();
+        for c in HexCoord::origin().range(4) {
+            for reflect in [
+                HexCoord::reflect_q,
+                HexCoord::reflect_r,
+                HexCoord::reflect_s,
+            ] {
+                let mirrored = reflect(&c);
+                assert_eq!(0, mirrored.q + mirrored.r + mirrored.s);
+                assert_eq!(origin.distance(&c), origin.distance(&mirrored));
+                assert_eq!(c, reflect(&mirrored));
+            }
+        }
+        // Tiles on the mirror line stay put
+        assert_eq!(HexCoord::new(2, -1), HexCoord::new(2, -1).reflect_q());
+        assert_eq!(HexCoord::new(-1, 2), HexCoord::new(-1, 2).reflect_r());
+        assert_eq!(HexCoord::new(2, 2), HexCoord::new(2, 2).reflect_s());
+        assert_eq!(HexCoord::new(3, 0), HexCoord::new(3, -3).reflect_q());
+    }
+
+    #[test]
+    fn components_round_trip() {
+        for c in HexCoord::new(5, -2).range(3) {
+            let [q, r, s] = c.components();
+            assert_eq!(0, q + r + s);
+            assert_eq!(Ok(c), HexCoord::from_components(c.components()));
+        }
+        assert_eq!(
+            Err(InvalidHexCoord([1, 1, 1])),
+            HexCoord::from_components([1, 1, 1])
+        );
+
+        // Overflowing sums are refused rather than wrapping or panicking
+        let extreme = [isize::MAX, isize::MAX, 2];
+        assert_eq!(
+            Err(InvalidHexCoord(extreme)),
+            HexCoord::from_components(extreme)
+        );
+        let extreme = [isize::MIN, isize::MIN, 0];
+        assert_eq!(
+            Err(InvalidHexCoord(extreme)),
+            HexCoord::from_components(extreme)
+        );
+        let c = HexCoord::new(isize::MAX, isize::MIN + 1);
+        assert_eq!(Ok(c), HexCoord::from_components(c.components()));
+    }
+
+    #[test]
+    fn rings_group_tiles_by_distance() {
+        let center = HexCoord::new(-2, 5);
+        let rings = center.rings(4);
+        assert_eq!(5, rings.len());
+        assert_eq!((0, vec![center]), rings[0]);
+        for (k, (ring, tiles)) in rings.iter().enumerate() {
+            assert_eq!(k, *ring);
+            assert_eq!(if k == 0 { 1 } else { 6 * k }, tiles.len());
+            assert!(tiles.iter().all(|c| center.distance(c) == k));
+        }
+    }
+
+    #[test]
+    fn from_coords_names_the_step_to_each_neighbor() {
+        for from in HexCoord::new(4, -7).range(2) {
+            for dir in DIRECTIONS {
+                assert_eq!(
+                    Some(*dir),
+                    Direction::from_coords(&from, &from.neighbor(*dir))
+                );
+                assert_eq!(*dir, Direction::between(&from, &from.neighbor(*dir)));
+            }
+            assert_eq!(None, Direction::from_coords(&from, &from));
+            for far in from.ring(2) {
+                assert_eq!(None, Direction::from_coords(&from, &far));
+                assert_eq!(Direction::None, Direction::between(&from, &far));
+            }
+        }
+    }
 }
@@ -0,0 +1,147 @@
+use std::{error::Error, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    hex::HexCoord,
+    storage::HexMap,
+    terrain::{MapLoadError, TileData, MAP_FORMAT_VERSION},
+};
+
+/// A map as it's written to disk: the [MAP_FORMAT_VERSION] it was saved with, then every tile, ordered by coordinate
+/// so the same map always saves the same way
+#[derive(Serialize, Deserialize)]
+struct SavedMap {
+    version: u32,
+    tiles: Vec<(HexCoord, TileData)>,
+}
+
+/// Write every tile of `map` to `path` as RON
+pub fn save_map(path: impl AsRef<Path>, map: &HexMap<TileData>) -> io::Result<()> {
+    let mut tiles: Vec<_> = map.iter().map(|(c, data)| (*c, *data)).collect();
+    tiles.sort_by_key(|(c, _)| (c.q, c.r));
+    let saved = SavedMap {
+        version: MAP_FORMAT_VERSION,
+        tiles,
+    };
+    let text =
+        ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()).map_err(invalid)?;
+    fs::write(path, text)
+}
+
+/// Read a map written by [save_map]. Saves from a newer format version, coordinates that break the `q + r + s = 0`
+/// invariant, heights that aren't finite, and tiles listed more than once are all refused as
+/// [io::ErrorKind::InvalidData]
+pub fn load_map(path: impl AsRef<Path>) -> io::Result<HexMap<TileData>> {
+    let saved: SavedMap = ron::from_str(&fs::read_to_string(path)?).map_err(invalid)?;
+    if saved.version > MAP_FORMAT_VERSION {
+        return Err(invalid(MapLoadError::UnsupportedVersion(saved.version)));
+    }
+    let mut map = HexMap::new();
+    for (c, data) in saved.tiles {
+        if !data.height.is_finite() {
+            return Err(invalid(format!("tile {c} has a height of {}", data.height)));
+        }
+        if map.insert(c, data).is_some() {
+            return Err(invalid(format!("tile {c} is listed more than once")));
+        }
+    }
+    Ok(map)
+}
+
+fn invalid(error: impl Into<Box<dyn Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{classify, height_at, TileKind};
+    use std::path::PathBuf;
+
+    /// A file in the temp directory that's removed again once the test is done with it, even if it fails.
+    /// The name includes the process id, so separate test runs can't trip over each other's files
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let file = format!("bevy-hex-map-io-{}-{name}.ron", std::process::id());
+            TempFile(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn saved_maps_load_back() {
+        let mut map = HexMap::new();
+        for c in HexCoord::new(2, -1).range(6) {
+            let height = height_at(&c, 11);
+            let kind = classify(height);
+            map.insert(c, TileData { kind, height });
+        }
+        let file = TempFile::new("saved");
+        save_map(&file.0, &map).unwrap();
+        assert_eq!(map, load_map(&file.0).unwrap());
+    }
+
+    #[test]
+    fn broken_maps_are_refused() {
+        let load = |name: &str, text: &str| {
+            let file = TempFile::new(&format!("broken-{name}"));
+            fs::write(&file.0, text).unwrap();
+            load_map(&file.0)
+        };
+        let good = load(
+            "good",
+            "(version: 2, tiles: [((q: 1, r: -1, s: 0), (kind: Grass, height: 0.5))])",
+        )
+        .unwrap();
+        assert_eq!(
+            Some(&TileData {
+                kind: TileKind::Grass,
+                height: 0.5
+            }),
+            good.get(&HexCoord::new(1, -1))
+        );
+
+        for (name, text) in [
+            (
+                "invariant",
+                "(version: 2, tiles: [((q: 1, r: 1, s: 0), (kind: Grass, height: 0.5))])",
+            ),
+            (
+                "height",
+                "(version: 2, tiles: [((q: 1, r: -1, s: 0), (kind: Grass, height: NaN))])",
+            ),
+            (
+                "twice",
+                "(version: 2, tiles: [((q: 0, r: 0, s: 0), (kind: Grass, height: 0.5)), ((q: 0, r: 0, s: 0), (kind: Hills, height: 2.0))])",
+            ),
+            ("garbage", "not a map"),
+            (
+                "unversioned",
+                "(tiles: [((q: 1, r: -1, s: 0), (kind: Grass, height: 0.5))])",
+            ),
+        ] {
+            let error = load(name, text).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, error.kind(), "{name}");
+        }
+
+        let future = format!(
+            "(version: {}, tiles: [((q: 1, r: -1, s: 0), (kind: Grass, height: 0.5))])",
+            MAP_FORMAT_VERSION + 1
+        );
+        let error = load("future", &future).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        let expected = format!("format version {}", MAP_FORMAT_VERSION + 1);
+        assert!(error.to_string().contains(&expected), "{error}");
+
+        let missing = load_map(&TempFile::new("missing").0).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, missing.kind());
+    }
+}
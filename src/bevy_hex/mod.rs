@@ -1,2 +1,7 @@
 pub mod geometry;
 pub mod hex;
+#[cfg(feature = "ron")]
+pub mod map_io;
+pub mod pathfinding;
+pub mod storage;
+pub mod terrain;
@@ -0,0 +1,467 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+};
+
+use super::hex::{Direction, HexCoord};
+
+/// Breadth first search outward from every source at once, recording how many steps each tile is from its nearest source.
+/// Only tiles that are `passable` are entered, and the search stops `max` steps out
+pub fn multi_source_distances(
+    sources: &[HexCoord],
+    passable: impl Fn(&HexCoord) -> bool,
+    max: usize,
+) -> HashMap<HexCoord, usize> {
+    let mut distances = HashMap::new();
+    let mut frontier = VecDeque::new();
+    for source in sources {
+        if distances.insert(*source, 0).is_none() {
+            frontier.push_back(*source);
+        }
+    }
+    while let Some(current) = frontier.pop_front() {
+        let next = distances[&current] + 1;
+        if next > max {
+            continue;
+        }
+        for neighbor in current.neighbors() {
+            if !distances.contains_key(&neighbor) && passable(&neighbor) {
+                distances.insert(neighbor, next);
+                frontier.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+/// Find every tile reachable from `start` without spending more than `budget`, along with the cheapest cost to get there.
+/// Entering a tile costs `entry_cost`, and tiles where that's `None` are impassable
+pub fn movement_range(
+    start: HexCoord,
+    budget: u32,
+    entry_cost: impl Fn(&HexCoord) -> Option<u32>,
+) -> HashMap<HexCoord, u32> {
+    let mut costs = HashMap::from([(start, 0)]);
+    // HexCoord isn't ordered, so we queue up the raw components alongside the cost
+    let mut frontier = BinaryHeap::from([Reverse((0u32, start.q, start.r))]);
+    while let Some(Reverse((cost, q, r))) = frontier.pop() {
+        let current = HexCoord::new(q, r);
+        if costs[&current] < cost {
+            // We already found a cheaper way here
+            continue;
+        }
+        for neighbor in current.neighbors() {
+            let Some(step) = entry_cost(&neighbor) else {
+                continue;
+            };
+            let next = cost.saturating_add(step);
+            if next <= budget && costs.get(&neighbor).is_none_or(|known| next < *known) {
+                costs.insert(neighbor, next);
+                frontier.push(Reverse((next, neighbor.q, neighbor.r)));
+            }
+        }
+    }
+    costs
+}
+
+/// The most tiles A* will expand before giving up; an unreachable goal on an infinite grid would otherwise search forever
+const MAX_EXPANSIONS: usize = 65_536;
+
+/// Find the cheapest path from `start` to `goal`, where entering a tile costs `cost`, and tiles where that's `None` are impassable.
+/// The path includes both ends, and is `None` if the goal can't be reached.
+/// Distance is used as the heuristic, so this finds the cheapest path as long as every step costs at least 1
+pub fn astar(
+    start: HexCoord,
+    goal: HexCoord,
+    cost: impl Fn(&HexCoord) -> Option<u32>,
+) -> Option<Vec<HexCoord>> {
+    let path = astar_with_costs(start, goal, cost)?;
+    Some(path.into_iter().map(|(c, _)| c).collect())
+}
+
+/// Like [astar], but pairs each step of the path with the total cost of getting there, for pacing movement by cost
+pub fn astar_with_costs(
+    start: HexCoord,
+    goal: HexCoord,
+    cost: impl Fn(&HexCoord) -> Option<u32>,
+) -> Option<Vec<(HexCoord, u32)>> {
+    let heuristic = |c: &HexCoord| c.distance(&goal) as u32;
+    let mut came_from = HashMap::new();
+    let mut costs = HashMap::from([(start, 0)]);
+    // HexCoord isn't ordered, so we queue up the raw components alongside the estimate
+    let mut frontier = BinaryHeap::from([Reverse((heuristic(&start), 0u32, start.q, start.r))]);
+    let mut expansions = 0;
+    while let Some(Reverse((_, so_far, q, r))) = frontier.pop() {
+        let current = HexCoord::new(q, r);
+        if current == goal {
+            break;
+        }
+        if costs[&current] < so_far {
+            // We already found a cheaper way here
+            continue;
+        }
+        expansions += 1;
+        if expansions > MAX_EXPANSIONS {
+            return None;
+        }
+        for neighbor in current.neighbors() {
+            let Some(step) = cost(&neighbor) else {
+                continue;
+            };
+            let next = so_far.saturating_add(step);
+            if costs.get(&neighbor).is_none_or(|known| next < *known) {
+                costs.insert(neighbor, next);
+                came_from.insert(neighbor, current);
+                let estimate = next.saturating_add(heuristic(&neighbor));
+                frontier.push(Reverse((estimate, next, neighbor.q, neighbor.r)));
+            }
+        }
+    }
+
+    // Walk backwards from the goal to recover the path
+    let mut path = vec![(goal, *costs.get(&goal)?)];
+    let mut current = goal;
+    while let Some(previous) = came_from.get(&current) {
+        path.push((*previous, costs[previous]));
+        current = *previous;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Whether `attacker` can hit `target`: it has to be no more than `range` steps away, and no tile along the way can rise
+/// above the straight line between the two of them
+pub fn can_target(
+    attacker: &HexCoord,
+    target: &HexCoord,
+    range: usize,
+    height_of: impl Fn(&HexCoord) -> f32,
+) -> bool {
+    let steps = attacker.distance(target);
+    if steps > range {
+        return false;
+    }
+    let (from, to) = (height_of(attacker), height_of(target));
+    let line = attacker.line_to(target);
+    // The two ends can't block each other
+    line.iter()
+        .enumerate()
+        .skip(1)
+        .take(steps.saturating_sub(1))
+        .all(|(i, tile)| {
+            let t = i as f32 / steps as f32;
+            height_of(tile) <= from + (to - from) * t
+        })
+}
+
+/// The nearest tile to `target` that's `passable`, searching no more than `max_radius` steps out.
+/// Ties go to whichever tile comes first going clockwise around the ring
+pub fn closest_passable(
+    target: &HexCoord,
+    passable: impl Fn(&HexCoord) -> bool,
+    max_radius: usize,
+) -> Option<HexCoord> {
+    target.spiral(max_radius).into_iter().find(|c| passable(c))
+}
+
+/// The shortest set of connections that joins every one of `nodes`, as if each could be reached from any other in a
+/// straight line costing its [HexCoord::distance]. Handy for laying out roads between towns.
+/// Built up with Prim's algorithm, starting from the first node, so there's one edge fewer than there are nodes
+pub fn mst(nodes: &[HexCoord]) -> Vec<(HexCoord, HexCoord)> {
+    let Some(first) = nodes.first() else {
+        return vec![];
+    };
+    let mut in_tree = vec![false; nodes.len()];
+    in_tree[0] = true;
+    // The cheapest way found so far to join each node to the tree, and which node in the tree that's from
+    let mut cheapest: Vec<_> = nodes.iter().map(|c| (first.distance(c), 0)).collect();
+    let mut edges = Vec::with_capacity(nodes.len() - 1);
+    for _ in 1..nodes.len() {
+        let Some(next) = (0..nodes.len())
+            .filter(|i| !in_tree[*i])
+            .min_by_key(|i| cheapest[*i].0)
+        else {
+            break;
+        };
+        in_tree[next] = true;
+        edges.push((nodes[cheapest[next].1], nodes[next]));
+        for (i, c) in nodes.iter().enumerate() {
+            let cost = nodes[next].distance(c);
+            if !in_tree[i] && cost < cheapest[i].0 {
+                cheapest[i] = (cost, next);
+            }
+        }
+    }
+    edges
+}
+
+/// A single order for a unit that can only walk straight ahead or turn on the spot
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    /// Step onto the tile in front
+    Forward,
+    /// Turn a sixth of the way round, clockwise
+    TurnCw,
+    /// Turn a sixth of the way round, counter-clockwise
+    TurnCcw,
+}
+
+/// Translate a `path` of neighboring tiles, like one from [astar], into the turns and steps that walk it, for a unit
+/// starting on the first tile facing `start_facing`. It turns the shorter way round before each step; a step between
+/// tiles that aren't neighbors can't be walked, so it's left out
+pub fn directions_to_commands(path: &[HexCoord], start_facing: Direction) -> Vec<Command> {
+    let mut commands = vec![];
+    let mut facing = start_facing;
+    for step in path.windows(2) {
+        let dir = Direction::between(&step[0], &step[1]);
+        if dir == Direction::None {
+            continue;
+        }
+        let turns = facing.rotation_to(dir);
+        let turn = if turns > 0 {
+            Command::TurnCw
+        } else {
+            Command::TurnCcw
+        };
+        commands.extend(std::iter::repeat_n(turn, turns.unsigned_abs()));
+        commands.push(Command::Forward);
+        facing = dir;
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::DIRECTIONS;
+
+    #[test]
+    fn multi_source_takes_nearest_source() {
+        let sources = [HexCoord::new(-3, 0), HexCoord::new(2, 1)];
+        let distances = multi_source_distances(&sources, |_| true, 4);
+        for c in HexCoord::origin().range(8) {
+            let nearest = sources.iter().map(|s| s.distance(&c)).min().unwrap();
+            if nearest <= 4 {
+                assert_eq!(Some(&nearest), distances.get(&c));
+            } else {
+                assert_eq!(None, distances.get(&c));
+            }
+        }
+    }
+
+    #[test]
+    fn multi_source_walks_around_obstacles() {
+        let wall = HexCoord::origin().north();
+        let distances = multi_source_distances(&[HexCoord::origin()], |c| *c != wall, 3);
+        assert_eq!(None, distances.get(&wall));
+        // Going around the wall takes two extra steps
+        assert_eq!(Some(&3), distances.get(&wall.north()));
+    }
+
+    #[test]
+    fn movement_range_respects_terrain_costs() {
+        // A road runs east of the origin, the row below is swamp, and everything else is impassable
+        let cost = |c: &HexCoord| match c.r {
+            0 => Some(1),
+            1 => Some(2),
+            _ => None,
+        };
+        let range = movement_range(HexCoord::origin(), 3, cost);
+        assert_eq!(Some(&0), range.get(&HexCoord::origin()));
+        assert_eq!(Some(&3), range.get(&HexCoord::new(3, 0)));
+        assert_eq!(None, range.get(&HexCoord::new(4, 0)));
+        assert_eq!(Some(&2), range.get(&HexCoord::new(0, 1)));
+        // Cheaper to walk the road, then step down into the swamp
+        assert_eq!(Some(&3), range.get(&HexCoord::new(1, 1)));
+        assert_eq!(None, range.get(&HexCoord::new(0, -1)));
+        assert!(range.values().all(|c| *c <= 3));
+    }
+
+    #[test]
+    fn path_costs_accumulate_along_the_path() {
+        // Hills on every other column make the straight line expensive
+        let cost = |c: &HexCoord| Some(if c.q % 2 == 0 { 1 } else { 3 });
+        let (start, goal) = (HexCoord::new(-3, 1), HexCoord::new(4, -2));
+        let path = astar_with_costs(start, goal, cost).unwrap();
+        assert_eq!((start, 0), path[0]);
+        assert_eq!(goal, path.last().unwrap().0);
+
+        let total: u32 = path.iter().skip(1).map(|(c, _)| cost(c).unwrap()).sum();
+        assert_eq!(total, path.last().unwrap().1);
+        for pair in path.windows(2) {
+            let ((from, before), (to, after)) = (pair[0], pair[1]);
+            assert_eq!(1, from.distance(&to));
+            assert_eq!(before + cost(&to).unwrap(), after);
+        }
+
+        let coords: Vec<_> = path.iter().map(|(c, _)| *c).collect();
+        assert_eq!(Some(coords), astar(start, goal, cost));
+    }
+
+    #[test]
+    fn ridge_blocks_line_of_fire() {
+        let attacker = HexCoord::origin();
+        let target = HexCoord::new(3, -3);
+        let ridge = HexCoord::new(2, -2);
+        let flat = |_: &HexCoord| 0.;
+        let ridged = |c: &HexCoord| if *c == ridge { 2. } else { 0. };
+        assert!(can_target(&attacker, &target, 3, flat));
+        assert!(!can_target(&attacker, &target, 2, flat));
+        assert!(!can_target(&attacker, &target, 3, ridged));
+        // Shooting down from high ground clears the ridge
+        let perched = |c: &HexCoord| match c {
+            c if *c == attacker => 8.,
+            c if *c == ridge => 2.,
+            _ => 0.,
+        };
+        assert!(can_target(&attacker, &target, 3, perched));
+        assert!(can_target(&attacker, &attacker, 0, ridged));
+    }
+
+    #[test]
+    fn closest_passable_steps_out_of_the_water() {
+        let shore = HexCoord::new(1, 0);
+        let lake = shore.northwest();
+        let is_land = |c: &HexCoord| *c == shore || c.q > 1;
+        assert_eq!(Some(shore), closest_passable(&lake, is_land, 3));
+        assert_eq!(Some(shore), closest_passable(&shore, is_land, 0));
+        assert_eq!(None, closest_passable(&lake, is_land, 0));
+        assert_eq!(None, closest_passable(&lake, |_| false, 5));
+    }
+
+    #[test]
+    fn commands_walk_an_l_shaped_path() {
+        let start = HexCoord::origin();
+        let mut path = vec![start];
+        for _ in 0..3 {
+            path.push(path.last().unwrap().north());
+        }
+        for _ in 0..2 {
+            path.push(path.last().unwrap().southeast());
+        }
+        let commands = directions_to_commands(&path, Direction::Northeast);
+        use Command::*;
+        assert_eq!(
+            vec![TurnCcw, Forward, Forward, Forward, TurnCw, TurnCw, Forward, Forward],
+            commands
+        );
+
+        // Playing the commands back retraces the path
+        let mut facing = DIRECTIONS
+            .iter()
+            .position(|d| *d == Direction::Northeast)
+            .unwrap();
+        let mut walked = vec![start];
+        for command in commands {
+            match command {
+                Forward => walked.push(walked.last().unwrap().neighbor(DIRECTIONS[facing])),
+                TurnCw => facing = (facing + 1) % 6,
+                TurnCcw => facing = (facing + 5) % 6,
+            }
+        }
+        assert_eq!(path, walked);
+    }
+
+    #[test]
+    fn mst_joins_towns_as_cheaply_as_possible() {
+        let towns = [
+            HexCoord::origin(),
+            HexCoord::new(5, 0),
+            HexCoord::new(2, 0),
+            HexCoord::new(0, 3),
+        ];
+        let roads = mst(&towns);
+        assert_eq!(3, roads.len());
+        let weight = |edges: &[(HexCoord, HexCoord)]| -> usize {
+            edges.iter().map(|(a, b)| a.distance(b)).sum()
+        };
+
+        // Every town is on the network
+        let mut joined = vec![towns[0]];
+        while let Some(next) =
+            roads
+                .iter()
+                .find_map(|(a, b)| match (joined.contains(a), joined.contains(b)) {
+                    (true, false) => Some(*b),
+                    (false, true) => Some(*a),
+                    _ => None,
+                })
+        {
+            joined.push(next);
+        }
+        assert_eq!(towns.len(), joined.len());
+
+        // And no other three roads that join them all are any shorter
+        let all: Vec<_> = (0..towns.len())
+            .flat_map(|i| (i + 1..towns.len()).map(move |j| (towns[i], towns[j])))
+            .collect();
+        let mut cheapest = usize::MAX;
+        for a in 0..all.len() {
+            for b in a + 1..all.len() {
+                for c in b + 1..all.len() {
+                    let edges = [all[a], all[b], all[c]];
+                    let mut reached = vec![towns[0]];
+                    for _ in 0..towns.len() {
+                        for (x, y) in &edges {
+                            if reached.contains(x) != reached.contains(y) {
+                                reached.push(if reached.contains(x) { *y } else { *x });
+                            }
+                        }
+                    }
+                    if reached.len() == towns.len() {
+                        cheapest = cheapest.min(weight(&edges));
+                    }
+                }
+            }
+        }
+        assert_eq!(8, cheapest);
+        assert_eq!(cheapest, weight(&roads));
+    }
+
+    #[test]
+    fn astar_finds_a_shortest_path_across_open_ground() {
+        let (start, goal) = (HexCoord::new(-2, 4), HexCoord::new(3, -1));
+        let path = astar(start, goal, |_| Some(1)).unwrap();
+        assert_eq!(start, path[0]);
+        assert_eq!(goal, *path.last().unwrap());
+        assert_eq!(start.distance(&goal) + 1, path.len());
+        assert!(path.windows(2).all(|pair| pair[0].distance(&pair[1]) == 1));
+    }
+
+    #[test]
+    fn astar_gives_up_on_a_walled_off_goal() {
+        let goal = HexCoord::new(2, 2);
+        let wall: Vec<_> = goal.ring(1);
+        // Keep the search to a small field, so it runs out of tiles rather than expansions
+        let cost =
+            |c: &HexCoord| (!wall.contains(c) && c.distance(&HexCoord::origin()) <= 8).then_some(1);
+        assert_eq!(None, astar(HexCoord::new(-3, 0), goal, cost));
+    }
+
+    #[test]
+    fn movement_range_grows_with_the_budget() {
+        // Hills every third column, and a lake to walk around
+        let cost = |c: &HexCoord| {
+            if c.distance(&HexCoord::new(2, -1)) <= 1 {
+                None
+            } else if c.q % 3 == 0 {
+                Some(3)
+            } else {
+                Some(1)
+            }
+        };
+        let mut previous = movement_range(HexCoord::origin(), 0, cost);
+        assert_eq!(HashMap::from([(HexCoord::origin(), 0)]), previous);
+        for budget in 1..8 {
+            let range = movement_range(HexCoord::origin(), budget, cost);
+            assert!(range.len() > previous.len());
+            for (c, spent) in &previous {
+                assert_eq!(Some(spent), range.get(c));
+            }
+            assert!(range
+                .keys()
+                .all(|c| cost(c).is_some() || *c == HexCoord::origin()));
+            previous = range;
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use super::hex::{Direction, HexCoord, DIRECTIONS};
+
+/// Data stored per tile, keyed by coordinate
+#[derive(Clone, PartialEq, Debug)]
+pub struct HexMap<T> {
+    tiles: HashMap<HexCoord, T>,
+}
+
+impl<T> Default for HexMap<T> {
+    fn default() -> Self {
+        HexMap {
+            tiles: HashMap::new(),
+        }
+    }
+}
+
+impl<T> HexMap<T> {
+    /// Construct an empty map
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value` at `c`, returning whatever was there before
+    pub fn insert(&mut self, c: HexCoord, value: T) -> Option<T> {
+        self.tiles.insert(c, value)
+    }
+
+    /// Take the value stored at `c` off the map, returning it if there was one
+    pub fn remove(&mut self, c: &HexCoord) -> Option<T> {
+        self.tiles.remove(c)
+    }
+
+    /// The value stored at `c`, if any
+    #[must_use]
+    pub fn get(&self, c: &HexCoord) -> Option<&T> {
+        self.tiles.get(c)
+    }
+
+    /// The value stored at `c`, if any, for changing in place
+    #[must_use]
+    pub fn get_mut(&mut self, c: &HexCoord) -> Option<&mut T> {
+        self.tiles.get_mut(c)
+    }
+
+    /// The number of tiles on the map
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Whether there are no tiles on the map at all
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Yield every tile on the map, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&HexCoord, &T)> {
+        self.tiles.iter()
+    }
+
+    /// Whether there's a tile at `c`
+    #[must_use]
+    pub fn contains(&self, c: &HexCoord) -> bool {
+        self.tiles.contains_key(c)
+    }
+
+    /// Yield the neighbors of `c` that are on the map, starting from North and going clockwise
+    pub fn neighbors_of<'a>(
+        &'a self,
+        c: &HexCoord,
+    ) -> impl Iterator<Item = (HexCoord, &'a T)> + 'a {
+        let c = *c;
+        DIRECTIONS.iter().filter_map(move |dir| {
+            let n = c.neighbor(*dir);
+            Some((n, self.get(&n)?))
+        })
+    }
+
+    /// Every pair of neighboring tiles on the map, each listed once with the lower `(q, r)` first, in no particular order
+    #[must_use]
+    pub fn edges(&self) -> Vec<(HexCoord, HexCoord)> {
+        self.tiles
+            .keys()
+            .flat_map(|c| {
+                let c = *c;
+                self.neighbors_of(&c)
+                    .map(|(n, _)| n)
+                    .filter(move |n| (c.q, c.r) < (n.q, n.r))
+                    .map(move |n| (c, n))
+            })
+            .collect()
+    }
+
+    /// The directions from `c` that lead off the edge of the map
+    #[must_use]
+    pub fn boundary_directions(&self, c: &HexCoord) -> Vec<Direction> {
+        DIRECTIONS
+            .iter()
+            .filter(|dir| !self.contains(&c.neighbor(**dir)))
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_only_inserted_tiles() {
+        let mut map = HexMap::new();
+        let origin = HexCoord::origin();
+        map.insert(origin, "center");
+        map.insert(origin.north(), "north");
+        assert!(map.contains(&origin));
+        assert!(map.contains(&origin.north()));
+        assert!(!map.contains(&origin.south()));
+        assert_eq!(None, map.remove(&origin.south()));
+
+        let neighbors: Vec<_> = map.neighbors_of(&origin).collect();
+        assert_eq!(vec![(origin.north(), &"north")], neighbors);
+        assert_eq!(5, map.boundary_directions(&origin).len());
+        assert!(!map.boundary_directions(&origin).contains(&Direction::North));
+
+        assert_eq!(Some("north"), map.remove(&origin.north()));
+        assert!(!map.contains(&origin.north()));
+        assert_eq!(6, map.boundary_directions(&origin).len());
+    }
+
+    #[test]
+    fn edges_list_each_adjacent_pair_once() {
+        let mut map = HexMap::new();
+        for c in HexCoord::origin().range(1) {
+            map.insert(c, ());
+        }
+        let edges = map.edges();
+        // Six spokes out from the center, and six around the rim
+        assert_eq!(12, edges.len());
+        for (a, b) in &edges {
+            assert_eq!(1, a.distance(b));
+            assert!(!edges.contains(&(*b, *a)));
+        }
+        assert!(HexMap::<()>::new().edges().is_empty());
+    }
+
+    #[test]
+    fn neighbors_of_skips_empty_slots() {
+        let mut map = HexMap::new();
+        let origin = HexCoord::origin();
+        map.insert(origin, 0);
+        // Fill every other neighbor, going clockwise from North
+        for (i, dir) in DIRECTIONS.iter().enumerate().step_by(2) {
+            map.insert(origin.neighbor(*dir), i);
+        }
+        let neighbors: Vec<_> = map.neighbors_of(&origin).collect();
+        assert_eq!(
+            vec![
+                (origin.north(), &0),
+                (origin.southeast(), &2),
+                (origin.southwest(), &4),
+            ],
+            neighbors
+        );
+
+        *map.get_mut(&origin.southeast()).unwrap() += 10;
+        assert_eq!(Some(&12), map.get(&origin.southeast()));
+        assert_eq!(None, map.get_mut(&origin.south()));
+    }
+}
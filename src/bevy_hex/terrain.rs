@@ -0,0 +1,1152 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use rand::prelude::*;
+
+use super::{
+    geometry,
+    hex::{Direction, HexCoord, DIRECTIONS},
+    pathfinding,
+    storage::HexMap,
+};
+
+/// The kinds of terrain a tile can be
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileKind {
+    Water,
+    Grass,
+    Forest,
+    Hills,
+}
+
+impl TileKind {
+    /// Every kind of terrain, from the lowest to the highest
+    pub const ALL: [TileKind; 4] = [
+        TileKind::Water,
+        TileKind::Grass,
+        TileKind::Forest,
+        TileKind::Hills,
+    ];
+
+    /// The color of the ground for this kind of terrain, as sRGB
+    #[must_use]
+    pub fn color(&self) -> [f32; 3] {
+        match self {
+            TileKind::Water => [0.286, 0.725, 0.902], // #49B9E6 (73, 185, 230)
+            TileKind::Grass => [0.698, 0.941, 0.329], // #B2F054 (178, 240, 84)
+            TileKind::Forest => [0.239, 0.478, 0.227], // #3D7A3A (61, 122, 58)
+            TileKind::Hills => [0.722, 0.522, 0.380], // #B88561 (184, 133, 97)
+        }
+    }
+
+    /// Where a tile of this kind sits when nothing says otherwise
+    #[must_use]
+    pub fn base_height(&self) -> f32 {
+        match self {
+            TileKind::Water => -0.5,
+            TileKind::Grass => 0.5,
+            TileKind::Forest => 0.7,
+            TileKind::Hills => 2.,
+        }
+    }
+}
+
+/// Everything we know about a single tile of terrain
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileData {
+    pub kind: TileKind,
+    pub height: f32,
+}
+
+/// How a single tile differs between two versions of a map
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileChange {
+    Added(TileData),
+    Removed(TileData),
+    Modified { before: TileData, after: TileData },
+}
+
+/// The version of the save format that [MapData::save_to] writes.
+/// Version 1 didn't store heights, so tiles loaded from it start at the usual height for their kind
+pub const MAP_FORMAT_VERSION: u32 = 2;
+
+/// A whole map of terrain, as it would be saved or loaded
+#[derive(Clone, PartialEq, Debug)]
+pub struct MapData {
+    /// The save format this map came from; anything loaded is migrated, so this is always current
+    pub version: u32,
+    pub tiles: HashMap<HexCoord, TileData>,
+}
+
+impl Default for MapData {
+    fn default() -> Self {
+        Self {
+            version: MAP_FORMAT_VERSION,
+            tiles: HashMap::new(),
+        }
+    }
+}
+
+/// Why a saved map couldn't be loaded
+#[derive(Debug)]
+pub enum MapLoadError {
+    Io(io::Error),
+    /// The save is from a newer version of the format than we know how to read
+    UnsupportedVersion(u32),
+    /// Something on the given line, counting from 1, didn't make sense
+    Malformed {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for MapLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapLoadError::Io(e) => write!(f, "couldn't read map: {e}"),
+            MapLoadError::UnsupportedVersion(v) => write!(
+                f,
+                "map is saved in format version {v}, but only versions up to {MAP_FORMAT_VERSION} are supported"
+            ),
+            MapLoadError::Malformed { line, reason } => write!(f, "line {line}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for MapLoadError {}
+
+impl From<io::Error> for MapLoadError {
+    fn from(e: io::Error) -> Self {
+        MapLoadError::Io(e)
+    }
+}
+
+fn kind_name(kind: TileKind) -> &'static str {
+    match kind {
+        TileKind::Water => "water",
+        TileKind::Grass => "grass",
+        TileKind::Forest => "forest",
+        TileKind::Hills => "hills",
+    }
+}
+
+fn parse_kind(name: &str) -> Option<TileKind> {
+    TileKind::ALL
+        .into_iter()
+        .find(|kind| kind_name(*kind) == name)
+}
+
+impl MapData {
+    /// Write the map out as text: a `version` header, then one `q,r,kind,height` line per tile, ordered by coordinate
+    pub fn save_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "version {MAP_FORMAT_VERSION}")?;
+        let mut tiles: Vec<_> = self.tiles.iter().collect();
+        tiles.sort_by_key(|(c, _)| (c.q, c.r));
+        for (c, data) in tiles {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                c.q,
+                c.r,
+                kind_name(data.kind),
+                data.height
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read a map written by [MapData::save_to], upgrading saves from older versions of the format as it goes
+    pub fn load_from(reader: impl BufRead) -> Result<MapData, MapLoadError> {
+        let mut lines = reader.lines().enumerate();
+        let malformed = |line: usize, reason: &str| MapLoadError::Malformed {
+            line: line + 1,
+            reason: reason.to_string(),
+        };
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| malformed(0, "missing version header"))?;
+        let version = header?
+            .strip_prefix("version ")
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .ok_or_else(|| malformed(0, "expected `version <number>`"))?;
+        if version > MAP_FORMAT_VERSION {
+            return Err(MapLoadError::UnsupportedVersion(version));
+        }
+
+        let mut map = MapData::default();
+        for (number, line) in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<_> = line.split(',').map(str::trim).collect();
+            let expected = if version == 1 { 3 } else { 4 };
+            if fields.len() != expected {
+                return Err(malformed(number, &format!("expected {expected} fields")));
+            }
+            let coord = |i: usize| {
+                fields[i]
+                    .parse::<isize>()
+                    .map_err(|_| malformed(number, "bad coordinate"))
+            };
+            let c = HexCoord::new(coord(0)?, coord(1)?);
+            let kind =
+                parse_kind(fields[2]).ok_or_else(|| malformed(number, "unknown tile kind"))?;
+            let height = match version {
+                // Heights weren't saved yet, so fill in the usual one for the kind
+                1 => kind.base_height(),
+                _ => fields[3]
+                    .parse()
+                    .map_err(|_| malformed(number, "bad height"))?,
+            };
+            map.tiles.insert(c, TileData { kind, height });
+        }
+        Ok(map)
+    }
+
+    /// Everything that would need to change to turn this map into `other`, ordered by coordinate
+    #[must_use]
+    pub fn diff(&self, other: &MapData) -> Vec<(HexCoord, TileChange)> {
+        let mut changes: Vec<_> = self
+            .tiles
+            .iter()
+            .filter_map(|(c, before)| match other.tiles.get(c) {
+                None => Some((*c, TileChange::Removed(*before))),
+                Some(after) if after != before => Some((
+                    *c,
+                    TileChange::Modified {
+                        before: *before,
+                        after: *after,
+                    },
+                )),
+                Some(_) => None,
+            })
+            .chain(
+                other
+                    .tiles
+                    .iter()
+                    .filter(|(c, _)| !self.tiles.contains_key(*c))
+                    .map(|(c, after)| (*c, TileChange::Added(*after))),
+            )
+            .collect();
+        changes.sort_by_key(|(c, _)| (c.q, c.r));
+        changes
+    }
+}
+
+/// A pseudo-random number for the tile at `c`, that's always the same for a given `seed`.
+/// Handy for picking decorations or resource amounts per tile, without having to keep any random state around
+#[must_use]
+pub fn tile_hash(seed: u64, c: &HexCoord) -> u64 {
+    mix(mix(mix(seed) ^ c.q as u64) ^ c.r as u64)
+}
+
+/// A small horizontal offset for the tile at `c`, no longer than `max`, that's always the same for a given `seed`.
+/// Nudging tiles by this breaks up the perfectly regular grid without opening up gaps between them
+#[must_use]
+pub fn jitter(c: &HexCoord, seed: u64, max: f32) -> [f32; 3] {
+    let hash = tile_hash(seed, c);
+    // Use the top and bottom halves of the hash to pick a direction and a distance
+    let angle = (hash >> 32) as f32 / u32::MAX as f32 * std::f32::consts::TAU;
+    let length = (hash & 0xFFFF_FFFF) as f32 / u32::MAX as f32 * max;
+    [angle.cos() * length, 0., angle.sin() * length]
+}
+
+/// splitmix64, which scrambles nearby values into wildly different ones
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Assign each tile to its nearest capital, by index into `capitals`, producing Voronoi-like territories.
+/// Ties go to whichever capital comes first, and nothing is assigned if there are no capitals
+#[must_use]
+pub fn assign_regions(capitals: &[HexCoord], tiles: &[HexCoord]) -> HashMap<HexCoord, usize> {
+    tiles
+        .iter()
+        .filter_map(|tile| {
+            let (nearest, _) = capitals
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, capital)| capital.distance(tile))?;
+            Some((*tile, nearest))
+        })
+        .collect()
+}
+
+/// Pick a random tile that isn't underwater, for placing units or resources, or `None` if the map is all water
+pub fn random_land_tile(rng: &mut impl Rng, map: &HexMap<TileData>) -> Option<HexCoord> {
+    map.iter()
+        .filter(|(_, data)| data.kind != TileKind::Water)
+        .map(|(c, _)| *c)
+        .choose(rng)
+}
+
+/// The closest tile of `biome` to `from`, no more than `max_radius` steps away, as found by
+/// [pathfinding::closest_passable]
+#[must_use]
+pub fn nearest_of_biome(
+    from: &HexCoord,
+    biome: TileKind,
+    map: &HexMap<TileData>,
+    max_radius: usize,
+) -> Option<HexCoord> {
+    pathfinding::closest_passable(
+        from,
+        |c| map.get(c).is_some_and(|data| data.kind == biome),
+        max_radius,
+    )
+}
+
+/// Scatter up to `count` points of interest, like capitals or ruins, over the land, keeping every pair at least
+/// `min_distance` apart. Candidates are picked at random and thrown out if they land too close to one already placed,
+/// giving up after a while, so a crowded map may get fewer than `count`
+pub fn scatter_pois(
+    map: &HexMap<TileData>,
+    count: usize,
+    min_distance: usize,
+    rng: &mut impl Rng,
+) -> Vec<HexCoord> {
+    const ATTEMPTS_PER_POI: usize = 30;
+    let land: Vec<_> = map
+        .iter()
+        .filter(|(_, data)| data.kind != TileKind::Water)
+        .map(|(c, _)| *c)
+        .collect();
+    let mut placed: Vec<HexCoord> = vec![];
+    for _ in 0..count * ATTEMPTS_PER_POI {
+        if placed.len() == count {
+            break;
+        }
+        let Some(candidate) = land.choose(rng) else {
+            break;
+        };
+        if placed.iter().all(|p| p.distance(candidate) >= min_distance) {
+            placed.push(*candidate);
+        }
+    }
+    placed
+}
+
+/// The connected region of tiles around `start` where `same` holds, like a body of water, as long as it's smaller
+/// than `max_size` tiles. Bigger regions give `None`, and the search stops as soon as it reaches `max_size`,
+/// so an ocean costs no more to rule out than a lake. If `same` doesn't hold for `start`, the region is empty
+pub fn flood_fill_capped(
+    start: HexCoord,
+    same: impl Fn(&HexCoord) -> bool,
+    max_size: usize,
+) -> Option<HashSet<HexCoord>> {
+    let mut region = HashSet::new();
+    if !same(&start) {
+        return Some(region);
+    }
+    region.insert(start);
+    let mut frontier = VecDeque::from([start]);
+    while let Some(current) = frontier.pop_front() {
+        for neighbor in current.neighbors() {
+            if !region.contains(&neighbor) && same(&neighbor) {
+                region.insert(neighbor);
+                if region.len() >= max_size {
+                    return None;
+                }
+                frontier.push_back(neighbor);
+            }
+        }
+    }
+    (region.len() < max_size).then_some(region)
+}
+
+/// How far a player start can move off of its ring to find land
+const START_SNAP_RADIUS: usize = 2;
+
+/// `count` starting tiles spread evenly around the ring `radius` steps out from the origin, for fair multiplayer maps.
+/// Each takes the ring tile nearest its share of the way round, then moves to the closest land tile nobody else has;
+/// any that can't find one within [START_SNAP_RADIUS] steps are left out
+pub fn player_starts(map: &HexMap<TileData>, count: usize, radius: usize) -> Vec<HexCoord> {
+    let ring = HexCoord::origin().ring(radius);
+    let mut starts: Vec<HexCoord> = vec![];
+    for i in 0..count {
+        let ideal = ring[(i * ring.len() + count / 2) / count % ring.len()];
+        let free_land = |c: &HexCoord| {
+            map.get(c).is_some_and(|data| data.kind != TileKind::Water) && !starts.contains(c)
+        };
+        if let Some(start) = pathfinding::closest_passable(&ideal, free_land, START_SNAP_RADIUS) {
+            starts.push(start);
+        }
+    }
+    starts
+}
+
+/// Summary statistics for a map, for tuning generation
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct MapStats {
+    pub counts: HashMap<TileKind, usize>,
+    pub min_height: f32,
+    pub max_height: f32,
+    pub mean_height: f32,
+    /// The fraction of tiles that are water, from 0 to 1
+    pub water_fraction: f32,
+}
+
+/// Count up each kind of tile and measure the spread of heights. An empty map summarizes to all zeros
+#[must_use]
+pub fn summarize(map: &HexMap<TileData>) -> MapStats {
+    if map.is_empty() {
+        return MapStats::default();
+    }
+    let mut stats = MapStats {
+        min_height: f32::INFINITY,
+        max_height: f32::NEG_INFINITY,
+        ..Default::default()
+    };
+    let mut total_height = 0.;
+    for (_, data) in map.iter() {
+        *stats.counts.entry(data.kind).or_default() += 1;
+        stats.min_height = stats.min_height.min(data.height);
+        stats.max_height = stats.max_height.max(data.height);
+        total_height += data.height;
+    }
+    let total = map.len() as f32;
+    stats.mean_height = total_height / total;
+    stats.water_fraction = stats
+        .counts
+        .get(&TileKind::Water)
+        .copied()
+        .unwrap_or_default() as f32
+        / total;
+    stats
+}
+
+/// Smooth out jagged borders between kinds of terrain, cellular automaton style.
+/// Each tile whose neighbors are mostly one other kind switches to that kind, with a chance equal to the share of its
+/// neighbors that agree, so lone specks almost always go but ragged edges only sometimes do. Heights are left alone
+#[must_use]
+pub fn smooth_kinds(map: &HexMap<TileData>, seed: u64) -> HexMap<TileData> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    // The map iterates in no particular order, so sort it to roll the same dice for the same seed every time
+    let mut tiles: Vec<_> = map.iter().collect();
+    tiles.sort_by_key(|(c, _)| (c.q, c.r));
+
+    let mut smoothed = HexMap::new();
+    for (c, data) in tiles {
+        let mut counts: HashMap<TileKind, usize> = HashMap::new();
+        let mut total = 0;
+        for (_, neighbor) in map.neighbors_of(c) {
+            *counts.entry(neighbor.kind).or_default() += 1;
+            total += 1;
+        }
+        let majority = counts
+            .into_iter()
+            .find(|(kind, count)| *kind != data.kind && count * 2 > total);
+        let mut data = *data;
+        if let Some((kind, count)) = majority {
+            if rng.gen_bool(count as f64 / total as f64) {
+                data.kind = kind;
+            }
+        }
+        smoothed.insert(*c, data);
+    }
+    smoothed
+}
+
+/// The tile id Tiled should use for each kind of terrain; 0 is reserved by Tiled for an empty cell
+fn tiled_id(kind: TileKind) -> u32 {
+    match kind {
+        TileKind::Water => 1,
+        TileKind::Grass => 2,
+        TileKind::Forest => 3,
+        TileKind::Hills => 4,
+    }
+}
+
+/// Export the map as a Tiled CSV layer: one line per row of "odd-r" offset coordinates, each a comma separated list of
+/// tile ids, with 0 for anything missing. Set the Tiled map up as hexagonal, staggered along Y, with odd rows staggered.
+/// The grid starts on an even row, even if that leaves the first row empty, so the stagger still lines up
+#[must_use]
+pub fn to_tiled_csv(map: &HexMap<TileData>) -> String {
+    let cells: HashMap<_, _> = map
+        .iter()
+        .map(|(c, data)| (c.to_offset(), tiled_id(data.kind)))
+        .collect();
+    let (Some(min_col), Some(max_col), Some(min_row), Some(max_row)) = (
+        cells.keys().map(|(col, _)| *col).min(),
+        cells.keys().map(|(col, _)| *col).max(),
+        cells.keys().map(|(_, row)| *row).min(),
+        cells.keys().map(|(_, row)| *row).max(),
+    ) else {
+        return String::new();
+    };
+    let min_row = min_row - (min_row & 1);
+    (min_row..=max_row)
+        .map(|row| {
+            (min_col..=max_col)
+                .map(|col| cells.get(&(col, row)).copied().unwrap_or(0).to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The heights that pure black and pure white map to in a heightmap image
+#[cfg(feature = "heightmap")]
+const HEIGHTMAP_RANGE: (f32, f32) = (-1., 3.);
+
+/// Read the height of every tile within `map_radius` of the origin from a grayscale image, where brighter is higher.
+/// Tiles are sampled at their "odd-r" offset coordinates, with the origin in the middle of the image, and the image is
+/// stretched to cover the whole map whatever its size
+#[cfg(feature = "heightmap")]
+pub fn from_heightmap(
+    path: impl AsRef<std::path::Path>,
+    map_radius: usize,
+) -> Result<HashMap<HexCoord, f32>, image::ImageError> {
+    let image = image::open(path)?.to_luma8();
+    Ok(sample_heightmap(&image, map_radius))
+}
+
+#[cfg(feature = "heightmap")]
+fn sample_heightmap(image: &image::GrayImage, map_radius: usize) -> HashMap<HexCoord, f32> {
+    let (low, high) = HEIGHTMAP_RANGE;
+    let span = (2 * map_radius).max(1) as f32;
+    // Offset coordinates within the map run from -map_radius to map_radius; scale that onto the pixels
+    let to_pixel = |i: isize, size: u32| {
+        let t = (i + map_radius as isize) as f32 / span;
+        (t * (size - 1) as f32).round() as u32
+    };
+    HexCoord::origin()
+        .range(map_radius)
+        .into_iter()
+        .map(|c| {
+            let (col, row) = c.to_offset();
+            let pixel =
+                image.get_pixel(to_pixel(col, image.width()), to_pixel(row, image.height()));
+            let brightness = pixel.0[0] as f32 / u8::MAX as f32;
+            (c, low + (high - low) * brightness)
+        })
+        .collect()
+}
+
+/// How steep the ground is at `c`, as the biggest drop in height to any neighbor, along with the direction of that drop.
+/// A tile with nothing lower around it, like the bottom of a pit, has a slope of 0 in [Direction::None]
+pub fn slope(c: &HexCoord, height_of: impl Fn(&HexCoord) -> f32) -> (f32, Direction) {
+    let here = height_of(c);
+    DIRECTIONS
+        .iter()
+        .map(|dir| (here - height_of(&c.neighbor(*dir)), *dir))
+        .filter(|(drop, _)| *drop > 0.)
+        .fold((0., Direction::None), |steepest, candidate| {
+            if candidate.0 > steepest.0 {
+                candidate
+            } else {
+                steepest
+            }
+        })
+}
+
+/// The share of the height difference that flows from a tile to its lowest neighbor each erosion step.
+/// Keeping it well under half stops a tile from overshooting and leapfrogging its neighbor
+const EROSION_RATE: f32 = 0.2;
+
+/// Wear the terrain down by moving material downhill, `iterations` times over.
+/// Each step, every tile sheds a little of its height to its lowest neighbor, so peaks wear down and valleys fill in.
+/// Material only ever moves between tiles on the map, so the total height is preserved
+pub fn erode(heights: &mut HashMap<HexCoord, f32>, iterations: usize) {
+    for _ in 0..iterations {
+        let mut flows = vec![];
+        for (c, height) in heights.iter() {
+            let lowest = c
+                .neighbors()
+                .filter_map(|n| Some((n, *heights.get(&n)?)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((n, low)) = lowest {
+                if low < *height {
+                    flows.push((*c, n, (height - low) * EROSION_RATE));
+                }
+            }
+        }
+        // Every tile moves based on the heights at the start of the step, so the order we visit them doesn't matter
+        for (from, to, amount) in flows {
+            *heights.get_mut(&from).unwrap() -= amount;
+            *heights.get_mut(&to).unwrap() += amount;
+        }
+    }
+}
+
+/// The lowest and highest ground [height_at] can produce. Layered noise rarely strays far from the middle, so most
+/// tiles land within half of this
+pub const NOISE_HEIGHT_RANGE: (f32, f32) = (-4., 4.);
+
+/// How many layers of ever finer noise are added together, each half the size and half as strong as the last
+const NOISE_OCTAVES: u32 = 3;
+
+/// How many times the coarsest layer of noise goes up and down per world unit. Smaller makes bigger continents
+const NOISE_FREQUENCY: f32 = 0.08;
+
+/// A random value in `0..1` for the whole-numbered point `(x, z)`
+fn lattice_value(seed: u64, x: i64, z: i64) -> f32 {
+    (mix(mix(mix(seed) ^ x as u64) ^ z as u64) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Smoothly varying noise in `0..1`, blending between random values at the whole-numbered points around `(x, z)`
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (tx, tz) = (x - x0, z - z0);
+    // Ease in and out of each point, so there are no creases along the lines between them
+    let (sx, sz) = (tx * tx * (3. - 2. * tx), tz * tz * (3. - 2. * tz));
+    let corner = |dx, dz| lattice_value(seed, x0 as i64 + dx, z0 as i64 + dz);
+    let near = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * sx;
+    let far = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * sx;
+    near + (far - near) * sz
+}
+
+/// The height of the ground at `c`, from noise sampled at the middle of the tile, so that neighbors have similar
+/// heights and the map forms coherent continents. It's always the same for a given `seed`, and stays within
+/// [NOISE_HEIGHT_RANGE]. The noise is laid out on a [geometry::LayoutMode::Hexagonal] grid, the only one where every
+/// neighbor is also a tile close by
+#[must_use]
+pub fn height_at(c: &HexCoord, seed: u64) -> f32 {
+    let [x, _, z] = geometry::center_with_layout(
+        geometry::LayoutMode::Hexagonal,
+        geometry::DEFAULT_HEX_RADIUS,
+        c,
+        &[0., 0., 0.],
+    );
+    let (mut total, mut strength, mut frequency, mut most) = (0., 1., NOISE_FREQUENCY, 0.);
+    for octave in 0..NOISE_OCTAVES {
+        // Each layer gets its own seed, so they don't all peak in the same places
+        let layer = seed.wrapping_add(octave as u64);
+        total += value_noise(layer, x * frequency, z * frequency) * strength;
+        most += strength;
+        strength *= 0.5;
+        frequency *= 2.;
+    }
+    let (low, high) = NOISE_HEIGHT_RANGE;
+    low + total / most * (high - low)
+}
+
+/// The kind of terrain for ground at `height`: sea below low tide, then grass, forest and hills as it rises
+#[must_use]
+pub fn classify(height: f32) -> TileKind {
+    if height < -0.5 {
+        TileKind::Water
+    } else if height < 0.7 {
+        TileKind::Grass
+    } else if height < 1.2 {
+        TileKind::Forest
+    } else {
+        TileKind::Hills
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_each_changed_tile() {
+        let grass = TileData {
+            kind: TileKind::Grass,
+            height: 0.5,
+        };
+        let mut before = MapData::default();
+        for c in HexCoord::origin().range(2) {
+            before.tiles.insert(c, grass);
+        }
+        let mut after = before.clone();
+        assert!(before.diff(&after).is_empty());
+
+        let hill = TileData {
+            kind: TileKind::Hills,
+            height: 2.,
+        };
+        after.tiles.insert(HexCoord::new(1, -1), hill);
+        assert_eq!(
+            vec![(
+                HexCoord::new(1, -1),
+                TileChange::Modified {
+                    before: grass,
+                    after: hill
+                }
+            )],
+            before.diff(&after)
+        );
+
+        after.tiles.remove(&HexCoord::origin());
+        after.tiles.insert(HexCoord::new(5, 5), grass);
+        let changes = before.diff(&after);
+        assert_eq!(3, changes.len());
+        assert!(changes.contains(&(HexCoord::origin(), TileChange::Removed(grass))));
+        assert!(changes.contains(&(HexCoord::new(5, 5), TileChange::Added(grass))));
+    }
+
+    #[test]
+    fn regions_split_along_the_midline() {
+        let capitals = [HexCoord::new(-3, 0), HexCoord::new(3, 0)];
+        let tiles = HexCoord::origin().range(6);
+        let regions = assign_regions(&capitals, &tiles);
+        assert_eq!(tiles.len(), regions.len());
+        for (tile, region) in &regions {
+            let (west, east) = (tile.distance(&capitals[0]), tile.distance(&capitals[1]));
+            match west.cmp(&east) {
+                std::cmp::Ordering::Less => assert_eq!(0, *region),
+                std::cmp::Ordering::Greater => assert_eq!(1, *region),
+                // Ties go to the first capital
+                std::cmp::Ordering::Equal => assert_eq!(0, *region),
+            }
+        }
+        assert_eq!(Some(&0), regions.get(&HexCoord::new(-1, 0)));
+        assert_eq!(Some(&1), regions.get(&HexCoord::new(1, 0)));
+        assert!(assign_regions(&[], &tiles).is_empty());
+    }
+
+    #[test]
+    fn random_land_tile_avoids_water() {
+        let mut rng = rand::thread_rng();
+        let water = TileData {
+            kind: TileKind::Water,
+            height: -0.5,
+        };
+        let mut map = HexMap::new();
+        for c in HexCoord::origin().range(4) {
+            map.insert(c, water);
+        }
+        assert_eq!(None, random_land_tile(&mut rng, &map));
+        assert_eq!(None, random_land_tile(&mut rng, &HexMap::new()));
+
+        let island = HexCoord::new(2, -1);
+        let grass = TileData {
+            kind: TileKind::Grass,
+            height: 0.5,
+        };
+        map.insert(island, grass);
+        for _ in 0..10 {
+            assert_eq!(Some(island), random_land_tile(&mut rng, &map));
+        }
+    }
+
+    #[test]
+    fn summarize_small_map() {
+        let mut map = HexMap::new();
+        let tiles = [
+            (TileKind::Water, -0.5),
+            (TileKind::Water, -0.5),
+            (TileKind::Grass, 0.5),
+            (TileKind::Hills, 2.5),
+        ];
+        for (c, (kind, height)) in HexCoord::origin().range(1).into_iter().zip(tiles) {
+            map.insert(c, TileData { kind, height });
+        }
+        let stats = summarize(&map);
+        assert_eq!(Some(&2), stats.counts.get(&TileKind::Water));
+        assert_eq!(Some(&1), stats.counts.get(&TileKind::Grass));
+        assert_eq!(Some(&1), stats.counts.get(&TileKind::Hills));
+        assert_eq!(None, stats.counts.get(&TileKind::Forest));
+        assert_eq!(-0.5, stats.min_height);
+        assert_eq!(2.5, stats.max_height);
+        assert_eq!(0.5, stats.mean_height);
+        assert_eq!(0.5, stats.water_fraction);
+        assert_eq!(MapStats::default(), summarize(&HexMap::new()));
+    }
+
+    #[test]
+    fn smoothing_shortens_borders() {
+        // Count each pair of neighboring tiles that differ once
+        let border = |map: &HexMap<TileData>| {
+            map.iter()
+                .map(|(c, data)| {
+                    map.neighbors_of(c)
+                        .filter(|(_, n)| n.kind != data.kind)
+                        .count()
+                })
+                .sum::<usize>()
+                / 2
+        };
+        // Water to the west, grass to the east, with a sprinkling of noise
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut map = HexMap::new();
+        for c in HexCoord::origin().range(8) {
+            let mut kind = if c.q < 0 {
+                TileKind::Water
+            } else {
+                TileKind::Grass
+            };
+            if rng.gen_bool(0.2) {
+                kind = if kind == TileKind::Water {
+                    TileKind::Grass
+                } else {
+                    TileKind::Water
+                };
+            }
+            map.insert(c, TileData { kind, height: 0. });
+        }
+        let smoothed = smooth_kinds(&map, 42);
+        assert_eq!(map.len(), smoothed.len());
+        assert!(border(&smoothed) < border(&map));
+        assert_eq!(smoothed, smooth_kinds(&map, 42));
+    }
+
+    #[test]
+    fn tiled_csv_lays_out_offset_rows() {
+        let center = HexCoord::new(0, 1);
+        let mut map = HexMap::new();
+        for c in center.range(1) {
+            map.insert(
+                c,
+                TileData {
+                    kind: TileKind::Grass,
+                    height: 0.5,
+                },
+            );
+        }
+        map.insert(
+            center.northwest(),
+            TileData {
+                kind: TileKind::Water,
+                height: -0.5,
+            },
+        );
+        let csv = to_tiled_csv(&map);
+        let grid: Vec<Vec<u32>> = csv
+            .lines()
+            .map(|line| line.split(',').map(|id| id.parse().unwrap()).collect())
+            .collect();
+        // Rows 0 through 2, and columns -1 through 1
+        assert_eq!(3, grid.len());
+        assert!(grid.iter().all(|row| row.len() == 3));
+        for (c, data) in map.iter() {
+            let (col, row) = c.to_offset();
+            assert_eq!(tiled_id(data.kind), grid[row as usize][(col + 1) as usize]);
+        }
+        assert_eq!(vec![0, 2, 2], grid[0]);
+        assert_eq!(vec![1, 2, 2], grid[1]);
+        assert_eq!("", to_tiled_csv(&HexMap::new()));
+    }
+
+    #[test]
+    fn scattered_pois_keep_their_distance() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut map = HexMap::new();
+        for c in HexCoord::origin().range(10) {
+            let kind = if c.q < -5 {
+                TileKind::Water
+            } else {
+                TileKind::Grass
+            };
+            map.insert(c, TileData { kind, height: 0. });
+        }
+        let pois = scatter_pois(&map, 6, 4, &mut rng);
+        assert_eq!(6, pois.len());
+        for (i, a) in pois.iter().enumerate() {
+            assert_eq!(TileKind::Grass, map.get(a).unwrap().kind);
+            for b in &pois[i + 1..] {
+                assert!(a.distance(b) >= 4);
+            }
+        }
+        // There's no room for a second point this far apart
+        assert_eq!(1, scatter_pois(&map, 3, 30, &mut rng).len());
+        assert!(scatter_pois(&HexMap::new(), 3, 1, &mut rng).is_empty());
+    }
+
+    #[cfg(feature = "heightmap")]
+    #[test]
+    fn heightmap_brightness_sets_height() {
+        // Black on the left half, white on the right, and one grey pixel in the middle
+        let mut image =
+            image::GrayImage::from_fn(5, 5, |x, _| image::Luma([if x < 2 { 0 } else { 255 }]));
+        image.put_pixel(2, 2, image::Luma([128]));
+
+        let heights = sample_heightmap(&image, 2);
+        assert_eq!(HexCoord::origin().range(2).len(), heights.len());
+        let (low, high) = HEIGHTMAP_RANGE;
+        assert_eq!(Some(&low), heights.get(&HexCoord::new(-2, 0)));
+        assert_eq!(Some(&high), heights.get(&HexCoord::new(2, 0)));
+        assert_eq!(Some(&high), heights.get(&HexCoord::new(2, -2)));
+        let middle = heights[&HexCoord::origin()];
+        assert!((middle - (low + high) / 2.).abs() < 0.05);
+
+        assert!(from_heightmap(std::env::temp_dir().join("no-such-heightmap.png"), 2).is_err());
+    }
+
+    #[test]
+    fn slope_points_downhill() {
+        // The ground falls away to the south, where r grows
+        let tilted = |c: &HexCoord| -(c.r as f32);
+        assert_eq!((1., Direction::South), slope(&HexCoord::new(2, -1), tilted));
+        // Falling away where q grows, the northeast and southeast drop equally, so the first going clockwise wins
+        let sloped = |c: &HexCoord| -0.5 * c.q as f32;
+        assert_eq!(
+            (0.5, Direction::Northeast),
+            slope(&HexCoord::origin(), sloped)
+        );
+        let pit = |c: &HexCoord| c.distance(&HexCoord::origin()) as f32;
+        assert_eq!((0., Direction::None), slope(&HexCoord::origin(), pit));
+    }
+
+    #[test]
+    fn erosion_smooths_a_bumpy_slope() {
+        // Sum up the squared height differences between every pair of neighbors
+        let roughness = |heights: &HashMap<HexCoord, f32>| {
+            heights
+                .iter()
+                .flat_map(|(c, h)| {
+                    c.neighbors()
+                        .filter_map(|n| heights.get(&n))
+                        .map(move |n| (h - n).powi(2))
+                })
+                .sum::<f32>()
+                / 2.
+        };
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut heights: HashMap<_, _> = HexCoord::origin()
+            .range(6)
+            .into_iter()
+            .map(|c| (c, c.q as f32 * 0.3 + rng.gen_range(-1.0..1.0)))
+            .collect();
+        let before = roughness(&heights);
+        let mass: f32 = heights.values().sum();
+
+        erode(&mut heights, 10);
+        assert!(roughness(&heights) < before);
+        assert!((heights.values().sum::<f32>() - mass).abs() < 1e-3);
+        assert!(heights.values().all(|h| h.is_finite()));
+    }
+
+    #[test]
+    fn saved_maps_load_back() {
+        let mut map = MapData::default();
+        for (c, kind) in HexCoord::origin().range(1).into_iter().zip([
+            TileKind::Water,
+            TileKind::Grass,
+            TileKind::Forest,
+            TileKind::Hills,
+        ]) {
+            map.tiles.insert(c, TileData { kind, height: 1.25 });
+        }
+        let mut saved = vec![];
+        map.save_to(&mut saved).unwrap();
+        assert_eq!(map, MapData::load_from(saved.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn version_one_maps_get_default_heights() {
+        let fixture = "version 1\n0,0,grass\n1,-1,hills\n\n-1,0,water\n";
+        let map = MapData::load_from(fixture.as_bytes()).unwrap();
+        assert_eq!(MAP_FORMAT_VERSION, map.version);
+        assert_eq!(3, map.tiles.len());
+        assert_eq!(
+            Some(&TileData {
+                kind: TileKind::Hills,
+                height: 2.
+            }),
+            map.tiles.get(&HexCoord::new(1, -1))
+        );
+        assert_eq!(-0.5, map.tiles[&HexCoord::new(-1, 0)].height);
+
+        let future = format!("version {}\n0,0,grass,0.5\n", MAP_FORMAT_VERSION + 1);
+        assert!(matches!(
+            MapData::load_from(future.as_bytes()),
+            Err(MapLoadError::UnsupportedVersion(v)) if v == MAP_FORMAT_VERSION + 1
+        ));
+        assert!(matches!(
+            MapData::load_from("version 2\n0,0,lava,1\n".as_bytes()),
+            Err(MapLoadError::Malformed { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn nearest_of_biome_finds_the_lone_forest() {
+        let mut map = HexMap::new();
+        for c in HexCoord::origin().range(5) {
+            map.insert(
+                c,
+                TileData {
+                    kind: TileKind::Grass,
+                    height: 0.5,
+                },
+            );
+        }
+        let from = HexCoord::new(-2, 1);
+        assert_eq!(None, nearest_of_biome(&from, TileKind::Forest, &map, 10));
+
+        let forest = HexCoord::new(2, -1);
+        map.insert(
+            forest,
+            TileData {
+                kind: TileKind::Forest,
+                height: 0.7,
+            },
+        );
+        assert_eq!(
+            Some(forest),
+            nearest_of_biome(&from, TileKind::Forest, &map, 10)
+        );
+        // Too far to see from here
+        assert_eq!(None, nearest_of_biome(&from, TileKind::Forest, &map, 3));
+        assert_eq!(
+            Some(from),
+            nearest_of_biome(&from, TileKind::Grass, &map, 0)
+        );
+    }
+
+    #[test]
+    fn player_starts_are_evenly_spaced() {
+        let mut map = HexMap::new();
+        for c in HexCoord::origin().range(9) {
+            map.insert(
+                c,
+                TileData {
+                    kind: TileKind::Grass,
+                    height: 0.5,
+                },
+            );
+        }
+        // Flood one of the spots a start would want, so it has to move
+        let flooded = HexCoord::origin().ring(6)[0];
+        map.insert(
+            flooded,
+            TileData {
+                kind: TileKind::Water,
+                height: -0.5,
+            },
+        );
+        for count in 2..=6 {
+            let starts = player_starts(&map, count, 6);
+            assert_eq!(count, starts.len());
+            assert!(!starts.contains(&flooded));
+            let gaps: Vec<_> = starts
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| {
+                    // Each start's nearest rival should be about as far off as everyone else's
+                    starts
+                        .iter()
+                        .enumerate()
+                        .filter(move |(j, _)| i != *j)
+                        .map(|(_, b)| a.distance(b))
+                        .min()
+                })
+                .collect();
+            let (closest, furthest) = (gaps.iter().min().unwrap(), gaps.iter().max().unwrap());
+            assert!(furthest - closest <= 2, "{count} starts: {gaps:?}");
+        }
+        assert!(player_starts(&HexMap::new(), 3, 4).is_empty());
+    }
+
+    #[test]
+    fn flood_fill_stops_at_the_cap() {
+        let lake_center = HexCoord::new(-6, 2);
+        let lake: HashSet<_> = lake_center.range(1).into_iter().collect();
+        // Everything east of q = 3 is open sea, as far as the search cares to look
+        let is_water = |c: &HexCoord| lake.contains(c) || c.q > 3;
+
+        assert_eq!(
+            Some(lake.clone()),
+            flood_fill_capped(lake_center, is_water, 20)
+        );
+        assert_eq!(
+            Some(lake.clone()),
+            flood_fill_capped(lake_center, is_water, 8)
+        );
+        assert_eq!(None, flood_fill_capped(lake_center, is_water, 7));
+        assert_eq!(None, flood_fill_capped(HexCoord::new(10, 0), is_water, 20));
+        assert_eq!(
+            Some(HashSet::new()),
+            flood_fill_capped(HexCoord::origin(), is_water, 20)
+        );
+    }
+
+    #[test]
+    fn tile_hash_is_stable_per_tile() {
+        let tiles = HexCoord::origin().range(6);
+        let hashes: HashSet<_> = tiles.iter().map(|c| tile_hash(7, c)).collect();
+        assert_eq!(tiles.len(), hashes.len());
+        for c in &tiles {
+            assert_eq!(tile_hash(7, c), tile_hash(7, &HexCoord::new(c.q, c.r)));
+            assert_ne!(tile_hash(7, c), tile_hash(8, c));
+        }
+        // Swapping q and r is a different tile, so it shouldn't hash the same
+        assert_ne!(
+            tile_hash(7, &HexCoord::new(2, -5)),
+            tile_hash(7, &HexCoord::new(-5, 2))
+        );
+    }
+
+    #[test]
+    fn noise_heights_are_repeatable_and_smooth() {
+        let tiles = HexCoord::origin().range(12);
+        let field = |seed| -> Vec<f32> { tiles.iter().map(|c| height_at(c, seed)).collect() };
+        assert_eq!(field(42), field(42));
+        assert_ne!(field(42), field(43));
+        let (low, high) = NOISE_HEIGHT_RANGE;
+        assert!(field(42).iter().all(|h| (low..=high).contains(h)));
+
+        // Neighbors are much closer in height than tiles picked from all over the map
+        let mean = |pairs: &[(HexCoord, HexCoord)]| {
+            let total: f32 = pairs
+                .iter()
+                .map(|(a, b)| (height_at(a, 42) - height_at(b, 42)).abs())
+                .sum();
+            total / pairs.len() as f32
+        };
+        let neighbors: Vec<_> = tiles
+            .iter()
+            .map(|c| (*c, c.neighbor(Direction::Northeast)))
+            .collect();
+        let distant: Vec<_> = tiles
+            .iter()
+            .map(|c| (*c, HexCoord::new(c.r * 3 + 40, -c.q * 2 - 25)))
+            .collect();
+        assert!(mean(&neighbors) * 2. < mean(&distant));
+    }
+
+    #[test]
+    fn classify_bands_rise_from_sea_to_hills() {
+        assert_eq!(TileKind::Water, classify(-2.));
+        assert_eq!(TileKind::Grass, classify(-0.2));
+        assert_eq!(TileKind::Grass, classify(0.5));
+        assert_eq!(TileKind::Forest, classify(1.));
+        assert_eq!(TileKind::Hills, classify(2.));
+    }
+
+    #[test]
+    fn every_kind_has_its_own_look() {
+        // Adding a kind means adding it here, which is a reminder to add it to ALL too
+        let order = |kind: &TileKind| match kind {
+            TileKind::Water => 0,
+            TileKind::Grass => 1,
+            TileKind::Forest => 2,
+            TileKind::Hills => 3,
+        };
+        let orders: Vec<_> = TileKind::ALL.iter().map(order).collect();
+        assert_eq!((0..4).collect::<Vec<_>>(), orders);
+
+        for (i, a) in TileKind::ALL.iter().enumerate() {
+            assert!(a.color().iter().all(|c| (0. ..=1.).contains(c)));
+            for b in &TileKind::ALL[i + 1..] {
+                assert_ne!(a.color(), b.color());
+                assert!(a.base_height() < b.base_height());
+            }
+        }
+    }
+
+    #[test]
+    fn jitter_is_bounded_and_reproducible() {
+        let max = 0.05;
+        for c in HexCoord::origin().range(10) {
+            let j = jitter(&c, 42, max);
+            assert!((j[0] * j[0] + j[2] * j[2]).sqrt() <= max + 1e-6);
+            assert_eq!(0., j[1]);
+            assert_eq!(j, jitter(&c, 42, max));
+        }
+        let c = HexCoord::new(3, -1);
+        assert_ne!(jitter(&c, 1, max), jitter(&c, 2, max));
+        assert_ne!(jitter(&c, 1, max), jitter(&c.north(), 1, max));
+    }
+}
@@ -1,95 +1,809 @@
 use bevy::{
+    core_pipeline::{clear_color::ClearColorConfig, Skybox},
+    ecs::{query::Has, system::SystemParam},
+    input::{
+        common_conditions::input_just_pressed,
+        mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+        InputSystem,
+    },
+    pbr::PointLightShadowMap,
     prelude::*,
-    render::{camera::Camera, mesh::Indices, render_resource::PrimitiveTopology},
+    reflect::TypePath,
+    render::{
+        camera::{Camera, RenderTarget},
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::{
+            AsBindGroup, Extent3d, PrimitiveTopology, ShaderRef, TextureDescriptor,
+            TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+            TextureViewDimension,
+        },
+        texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    },
+    tasks::{AsyncComputeTaskPool, Task},
+    time::TimeUpdateStrategy,
+    window::PrimaryWindow,
 };
+use futures_lite::future;
 use rand::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
-use bevy_hex_example::{geometry, hex};
+use bevy_hex_example::{
+    geometry, hex, pathfinding,
+    storage::HexMap,
+    terrain::{self, TileData, TileKind},
+};
 
 fn main() {
+    let quality = RenderQuality::default();
     App::new()
-        .insert_resource(Msaa::Sample4)
+        .insert_resource(quality.msaa())
+        .insert_resource(PointLightShadowMap {
+            size: quality.shadow_map_size().unwrap_or(1024),
+        })
+        .insert_resource(quality)
         .add_plugins(DefaultPlugins)
-        .add_systems(Startup, sample_level)
-        .add_systems(Update, (keyboard_controls, water_ripple))
+        .add_plugins(MaterialPlugin::<CelMaterial>::default())
+        .add_plugins(MaterialPlugin::<ReflectiveWater>::default())
+        .init_resource::<WaterScroll>()
+        .init_resource::<ShowAxes>()
+        .init_resource::<Tide>()
+        .init_resource::<WaterLevel>()
+        .init_resource::<HoveredHex>()
+        .init_resource::<SelectedHex>()
+        .init_resource::<SelectedRegion>()
+        .init_resource::<MapConfig>()
+        .init_resource::<EditHistory>()
+        .init_resource::<MaterialCache>()
+        .init_resource::<Backdrop>()
+        .init_resource::<BorderStyle>()
+        .init_resource::<IdleBob>()
+        .init_resource::<TileIndex>()
+        .init_resource::<InputRecorder>()
+        .init_resource::<PendingAction>()
+        .init_resource::<CameraMovement>()
+        .init_resource::<BevelConfig>()
+        .init_resource::<CameraMode>()
+        .init_resource::<OrbitSettings>()
+        .init_resource::<ZoomLimits>()
+        .add_event::<ClearRegion>()
+        .add_event::<TileClicked>()
+        .add_systems(
+            Startup,
+            (
+                spawn_camera,
+                sample_level,
+                spawn_coordinate_readout,
+                spawn_axis_labels,
+                spawn_selection_ring,
+                setup_resource_icons,
+                setup_reflections,
+            ),
+        )
+        .add_systems(PostStartup, frame_map)
+        .add_systems(
+            PreUpdate,
+            // Picking goes first, so a replay can overrule it and a recording can see it
+            (picking, replay_input, toggle_recording, record_input)
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    toggle_camera_mode,
+                    toggle_camera_snap,
+                    keyboard_controls.run_if(resource_equals(CameraMode::Fly)),
+                    snap_camera_to_tile.run_if(resource_equals(CameraMode::Fly)),
+                    orbit_controls.run_if(resource_equals(CameraMode::Orbit)),
+                    camera_zoom,
+                )
+                    .chain(),
+                (tide, flood_tiles, water_ripple).chain(),
+                (
+                    (
+                        adjust_bevel,
+                        rebuild_hex_meshes.run_if(resource_changed::<BevelConfig>()),
+                        scroll_water,
+                    )
+                        .chain(),
+                    follow_reflection,
+                ),
+                (flash_random_tile, tile_flash),
+                (toggle_axes, draw_axes, place_axis_labels),
+                finish_map_generation,
+                apply_cel_shading,
+                (index_tiles, clear_regions).chain(),
+                (claim_territory, draw_ownership_borders),
+                frame_map.run_if(
+                    input_just_pressed(KeyCode::C).or_else(resource_removed::<MapGeneration>()),
+                ),
+                (highlight_hovered_kind, highlight_hovered_tile)
+                    .chain()
+                    .before(tile_flash),
+                coordinate_readout,
+                (edit_terrain, undo_redo),
+                (animate_heights, idle_bob),
+                (
+                    emit_tile_clicks,
+                    select_hovered,
+                    place_selection_ring,
+                    draw_selected_region,
+                )
+                    .chain(),
+                restyle_selection_ring.run_if(resource_changed::<BorderStyle>()),
+                (spawn_resource_icons, cull_distant_details),
+                spawn_token,
+                (click_to_move, follow_path, place_tokens).chain(),
+                (aim_area_action, preview_area, draw_area_preview).chain(),
+            ),
+        )
+        // Once everything that moves or edits tiles is done for the frame
+        .add_systems(PostUpdate, rebuild_chunks)
         .run();
 }
 
+/// What's drawn behind the map
+#[derive(Resource)]
+pub struct Backdrop {
+    pub clear_color: Color,
+    /// If set, draw a sky that fades from the clear color at the horizon up to this color overhead
+    pub sky: Option<Color>,
+}
+
+impl Default for Backdrop {
+    fn default() -> Self {
+        Backdrop {
+            clear_color: Color::rgb(0.75, 0.87, 0.95),
+            sky: Some(Color::rgb(0.27, 0.5, 0.85)),
+        }
+    }
+}
+
+/// Build a cubemap that fades from `horizon` up to `zenith`
+fn gradient_skybox(horizon: Color, zenith: Color) -> Image {
+    const SIZE: u32 = 16;
+    let mut data = Vec::with_capacity((SIZE * SIZE * 6 * 4) as usize);
+    // Faces are stacked +x, -x, +y, -y, +z, -z
+    for face in 0..6 {
+        for row in 0..SIZE {
+            let t = match face {
+                2 => 1.,
+                3 => 0.,
+                // Side faces run top to bottom, and the horizon is halfway down
+                _ => (1. - 2. * (row as f32 + 0.5) / SIZE as f32).max(0.),
+            };
+            let color = lerp_color(horizon, zenith, t).as_rgba_u8();
+            for _ in 0..SIZE {
+                data.extend(color);
+            }
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE * 6,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.reinterpret_stacked_2d_as_array(6);
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    image
+}
+
+/// Build a caustics texture for the water: bright wavy lines over a slightly darker ground, made from waves with a
+/// whole number of periods across it so it tiles seamlessly as it scrolls
+fn caustics_texture() -> Image {
+    const SIZE: u32 = 64;
+    let step = std::f32::consts::TAU / SIZE as f32;
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let (u, v) = (x as f32 * step, y as f32 * step);
+            // Two waves, each bent by the other, light up where either of them crosses zero
+            let wave = (2. * u + (3. * v).sin()).sin() * (3. * v + (2. * u).sin()).sin();
+            let light = 0.75 + 0.25 * (1. - wave.abs()).powi(8);
+            data.extend(Color::rgb(light, light, light).as_rgba_u8());
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+fn spawn_camera(
+    mut commands: Commands,
+    backdrop: Res<Backdrop>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut camera = commands.spawn(Camera3dBundle {
+        camera_3d: Camera3d {
+            clear_color: ClearColorConfig::Custom(backdrop.clear_color),
+            ..Default::default()
+        },
+        transform: Transform::from_translation(Vec3::new(-10.0, 15., 0.0))
+            .looking_at(Vec3::default(), Vec3::Y),
+        ..Default::default()
+    });
+    if let Some(sky) = backdrop.sky {
+        camera.insert(Skybox(
+            images.add(gradient_skybox(backdrop.clear_color, sky)),
+        ));
+    }
+}
+
 fn sample_level(
     mut commands: Commands,
+    config: Res<MapConfig>,
+    quality: Res<RenderQuality>,
+    bevel: Res<BevelConfig>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: TileMaterials,
 ) {
     // add entities to the world
-    commands
-        // camera
-        .spawn(Camera3dBundle {
-            transform: Transform::from_translation(Vec3::new(-10.0, 15., 0.0))
-                .looking_at(Vec3::default(), Vec3::Y),
-            ..Default::default()
-        });
-
     commands
         // light
         .spawn(PointLightBundle {
+            point_light: PointLight {
+                shadows_enabled: quality.shadow_map_size().is_some(),
+                ..Default::default()
+            },
             transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
             ..Default::default()
         });
 
-    let colors = [
-        Color::rgb(0.286, 0.725, 0.902), // Water #49B9E6 (73, 185, 230)
-        Color::rgb(0.698, 0.941, 0.329), // Grass #B2F054 (178, 240, 84)
-        Color::rgb(0.722, 0.522, 0.380), // Hills ##B88561 (184, 133, 97)
-    ];
     // Generate our hex mesh
-    let mesh = meshes.add(generate_hex_mesh()).clone();
-    let mut rng = rand::thread_rng();
-    for q in -15..15 {
-        for r in -15..15 {
-            let tile = rng.gen_range(0..10);
-            let tile = if (1..5).contains(&tile) {
-                0
-            } else if (5..7).contains(&tile) {
-                1
-            } else {
-                2
-            };
-            let color = colors[tile];
-            let height = match tile {
-                0 => 0.,
-                1 => 0.5 + rng.gen_range(-0.2..0.2),
-                2 => 2. + rng.gen_range(-0.5..0.5),
-                _ => unreachable!(),
-            };
-            let pos = geometry::center(1.0, &hex::HexCoord::new(q, r), &[0., height, 0.]);
+    let mesh = meshes.add(generate_hex_mesh(&bevel)).clone();
+    // Water gets its own copy of the mesh, so we can scroll its UVs without affecting land
+    let water_mesh = generate_hex_mesh(&bevel);
+    let water_uvs = match water_mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => uvs.clone(),
+        _ => vec![],
+    };
+    let water = WaterMesh {
+        mesh: meshes.add(water_mesh),
+        uvs: water_uvs,
+    };
+    let land = LandMesh(mesh);
+    // Every water tile gets the shared material for the water's color, so the caustics only need adding to that one
+    let water_material = materials.shared(kind_color(TileKind::Water));
+    if let Some(material) = materials.assets.get_mut(&water_material) {
+        material.base_color_texture = Some(images.add(caustics_texture()));
+    }
 
-            let mut cmd = commands.spawn(PbrBundle {
-                mesh: mesh.clone(),
-                material: materials.add(color.into()),
-                transform: Transform::from_translation(Vec3::new(pos[0], pos[1], pos[2])),
-                ..Default::default()
+    if config.background {
+        // Tiles are spawned by `finish_map_generation` once this is done
+        let config = config.clone();
+        let task = AsyncComputeTaskPool::get().spawn(async move { generate_map(&config) });
+        commands.insert_resource(MapGeneration(task));
+    } else {
+        spawn_tiles(
+            &mut commands,
+            &config,
+            &land,
+            &water,
+            &mut meshes,
+            &mut materials,
+            generate_map(&config),
+        );
+    }
+    commands.insert_resource(land);
+    commands.insert_resource(water);
+}
+
+/// How much rendering work to spend on looking good, to suit the hardware
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RenderQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl RenderQuality {
+    /// How many samples to take per pixel to smooth jagged edges
+    pub fn msaa(self) -> Msaa {
+        match self {
+            RenderQuality::Low => Msaa::Off,
+            RenderQuality::Medium => Msaa::Sample2,
+            RenderQuality::High => Msaa::Sample4,
+        }
+    }
+
+    /// The resolution of the light's shadow maps, or `None` to skip shadows entirely
+    pub fn shadow_map_size(self) -> Option<usize> {
+        match self {
+            RenderQuality::Low => None,
+            RenderQuality::Medium => Some(1024),
+            RenderQuality::High => Some(2048),
+        }
+    }
+
+    /// Whether to hide small details, like resource icons, once they're far enough from the camera to barely show
+    pub fn lod(self) -> bool {
+        self != RenderQuality::High
+    }
+}
+
+/// How the sample lays its tiles out. Paths, area shapes, borders and the axes all step between neighboring
+/// coordinates, so they only line up with what's drawn when every neighbor is a tile close by
+pub const MAP_LAYOUT: geometry::LayoutMode = geometry::LayoutMode::Hexagonal;
+
+/// Settings for generating the sample map
+#[derive(Resource, Clone)]
+pub struct MapConfig {
+    /// Tiles are generated for offset columns and rows in `-size..size`, a rectangle of tiles on the [MAP_LAYOUT]
+    pub size: isize,
+    /// Generate the map on a background thread, so it doesn't hold up the first frame
+    pub background: bool,
+    /// Seeds any random choices that need to be the same every time for a given map
+    pub seed: u64,
+    /// The furthest each tile is nudged from its place on the grid, to break up the uniformity
+    pub jitter: f32,
+    /// Draw tiles with the flat, banded [CelMaterial] instead of the usual PBR shading.
+    /// Cel shaded tiles keep the color they were spawned with: edits and the tide still raise, lower and flood them,
+    /// but repainting, flooding's water color, highlights and flashes only show on the usual material
+    pub cel_shading: bool,
+    /// Give each land tile one of a few shades of its kind's color, picked by its coordinate, so the ground isn't
+    /// perfectly flat. Tiles of the same shade share a material from the [MaterialCache], so this only adds a couple
+    /// of materials per kind
+    pub shade_variation: bool,
+    /// Mirror the scene in the water's surface, at the cost of drawing everything a second time
+    pub reflections: bool,
+    /// Merge the land into one mesh per [CHUNK_SIZE] square of tiles, rather than drawing each tile on its own.
+    /// This is far quicker for big maps. Chunks are rebuilt as their tiles are edited or flooded, but merged land
+    /// doesn't bob, and has no material of its own to show highlights, flashes, cel shading or bevel changes.
+    /// Water is still drawn tile by tile, so it can ripple
+    pub chunked: bool,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        MapConfig {
+            size: 15,
+            background: true,
+            seed: rand::thread_rng().gen(),
+            jitter: 0.05,
+            cel_shading: false,
+            shade_variation: false,
+            reflections: false,
+            chunked: false,
+        }
+    }
+}
+
+/// The terrain for a single tile, before it's been spawned
+pub struct TileSpec {
+    pub coord: hex::HexCoord,
+    pub kind: TileKind,
+    pub height: f32,
+}
+
+/// Pick the terrain for every tile on the map from the noise for [MapConfig::seed], so it forms continents
+fn generate_map(config: &MapConfig) -> Vec<TileSpec> {
+    let mut tiles = vec![];
+    for row in -config.size..config.size {
+        for col in -config.size..config.size {
+            let coord = hex::HexCoord::from_offset(col, row);
+            let height = terrain::height_at(&coord, config.seed);
+            tiles.push(TileSpec {
+                coord,
+                kind: terrain::classify(height),
+                height,
             });
+        }
+    }
+    tiles
+}
+
+/// The color of the ground for each kind of tile
+fn kind_color(kind: TileKind) -> Color {
+    let [r, g, b] = kind.color();
+    Color::rgb(r, g, b)
+}
 
-            if tile == 0 {
-                cmd.insert(Water);
+/// How many shades each kind of land picks between, with [MapConfig::shade_variation]
+const PALETTE_SIZE: usize = 3;
+
+/// A few shades of each kind's color, from a touch darker to a touch lighter
+fn kind_palette(kind: TileKind) -> [Color; PALETTE_SIZE] {
+    let base = kind_color(kind);
+    [
+        lerp_color(base, Color::BLACK, 0.08),
+        base,
+        lerp_color(base, Color::WHITE, 0.08),
+    ]
+}
+
+/// The color for a tile of `kind` at `coord`. With [MapConfig::shade_variation] on, land picks a shade from its
+/// palette that's always the same for a given coordinate and seed; water stays one color, since the tide repaints it
+fn tile_color(config: &MapConfig, kind: TileKind, coord: &hex::HexCoord) -> Color {
+    if !config.shade_variation || kind == TileKind::Water {
+        return kind_color(kind);
+    }
+    kind_palette(kind)[(terrain::tile_hash(config.seed, coord) % PALETTE_SIZE as u64) as usize]
+}
+
+/// The materials tiles are spawned with, one for each color, so a map only allocates the colors in its palettes
+#[derive(Resource, Default)]
+pub struct MaterialCache {
+    shared: HashMap<u32, Handle<StandardMaterial>>,
+    /// The brighter copies [highlight_hovered_tile] swaps in, by the color they brighten
+    highlights: HashMap<u32, Handle<StandardMaterial>>,
+}
+
+/// The [MaterialCache] along with the materials themselves, for systems that spawn or recolor tiles
+#[derive(SystemParam)]
+pub struct TileMaterials<'w> {
+    cache: ResMut<'w, MaterialCache>,
+    pub assets: ResMut<'w, Assets<StandardMaterial>>,
+}
+
+impl TileMaterials<'_> {
+    /// The shared material for `color`, adding it the first time it's asked for
+    fn shared(&mut self, color: Color) -> Handle<StandardMaterial> {
+        let assets = &mut self.assets;
+        self.cache
+            .shared
+            .entry(color.as_rgba_u32())
+            .or_insert_with(|| assets.add(color.into()))
+            .clone()
+    }
+
+    /// A brighter copy of the material behind `handle`, for lighting up one tile. There's one for each color, which is
+    /// refreshed from `handle` every time it's handed out, so it picks up whatever else has changed on the material
+    fn highlight(&mut self, handle: &Handle<StandardMaterial>) -> Option<Handle<StandardMaterial>> {
+        let mut bright = self.assets.get(handle)?.clone();
+        let key = bright.base_color.as_rgba_u32();
+        bright.base_color = lerp_color(bright.base_color, Color::WHITE, HOVER_BRIGHTEN);
+        if let Some(highlight) = self.cache.highlights.get(&key) {
+            if let Some(material) = self.assets.get_mut(highlight) {
+                *material = bright;
+                return Some(highlight.clone());
             }
         }
+        let highlight = self.assets.add(bright);
+        self.cache.highlights.insert(key, highlight.clone());
+        Some(highlight)
+    }
+
+    /// Whether `handle` is one of the copies handed out by [TileMaterials::highlight]
+    fn is_highlight(&self, handle: &Handle<StandardMaterial>) -> bool {
+        self.cache
+            .highlights
+            .values()
+            .any(|highlight| highlight == handle)
+    }
+
+    /// The material to recolor a single entity with. One that's still on a shared material, or a highlight, is given a
+    /// copy of its own first, so the other tiles of its shade are left alone
+    fn own(&mut self, handle: &mut Handle<StandardMaterial>) -> Option<&mut StandardMaterial> {
+        let mut cached = self
+            .cache
+            .shared
+            .values()
+            .chain(self.cache.highlights.values());
+        if cached.any(|shared| shared == handle) {
+            let copy = self.assets.get(&*handle)?.clone();
+            *handle = self.assets.add(copy);
+        }
+        self.assets.get_mut(&*handle)
+    }
+}
+
+/// The mesh shared by all land tiles
+#[derive(Resource)]
+pub struct LandMesh(pub Handle<Mesh>);
+
+/// How many tiles across each merged chunk of land is, with [MapConfig::chunked]
+const CHUNK_SIZE: isize = 16;
+
+/// A merged mesh drawing a whole chunk of land tiles, by which chunk it is
+#[derive(Component)]
+pub struct TileChunk(pub (isize, isize));
+
+/// A land tile that's drawn by its [TileChunk], rather than a mesh of its own
+#[derive(Component)]
+pub struct Chunked;
+
+/// The chunk the tile at `c` is merged into. Chunks are squares of offset coordinates, so they're squares of tiles on
+/// the [MAP_LAYOUT]
+fn chunk_of(c: &hex::HexCoord) -> (isize, isize) {
+    let (col, row) = c.to_offset();
+    (col.div_euclid(CHUNK_SIZE), row.div_euclid(CHUNK_SIZE))
+}
+
+/// The color a chunked tile is drawn in, which is the water's color once it's flooded
+fn chunked_color(tile: &Tile, is_water: bool) -> [f32; 4] {
+    if is_water {
+        kind_color(TileKind::Water).as_rgba_f32()
+    } else {
+        tile.color.as_rgba_f32()
+    }
+}
+
+/// Hand a [geometry::ChunkMesh] over to the renderer
+fn chunk_mesh(chunk: geometry::ChunkMesh) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(chunk.indices)));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, chunk.positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, chunk.normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, chunk.uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, chunk.colors);
+    mesh
+}
+
+/// A map being generated in the background
+#[derive(Resource)]
+pub struct MapGeneration(pub Task<Vec<TileSpec>>);
+
+/// Spawn an entity for each generated tile
+fn spawn_tiles(
+    commands: &mut Commands,
+    config: &MapConfig,
+    land: &LandMesh,
+    water: &WaterMesh,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut TileMaterials,
+    tiles: Vec<TileSpec>,
+) {
+    let mut chunks: HashMap<(isize, isize), Vec<_>> = HashMap::new();
+    for TileSpec {
+        coord,
+        kind,
+        height,
+    } in tiles
+    {
+        let color = tile_color(config, kind, &coord);
+        let [dx, _, dz] = terrain::jitter(&coord, config.seed, config.jitter);
+        let pos = geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &coord,
+            &[dx, height, dz],
+        );
+        if config.chunked && kind != TileKind::Water {
+            // The tile still gets an entity, so everything can find it, but its chunk does the drawing
+            commands.spawn((
+                SpatialBundle::from_transform(Transform::from_translation(Vec3::from(pos))),
+                Tile {
+                    coord,
+                    kind,
+                    height,
+                    color,
+                },
+                Chunked,
+            ));
+            chunks
+                .entry(chunk_of(&coord))
+                .or_default()
+                .push((pos, color.as_rgba_f32()));
+            continue;
+        }
+
+        let mut cmd = commands.spawn(PbrBundle {
+            mesh: if kind == TileKind::Water {
+                water.mesh.clone()
+            } else {
+                land.0.clone()
+            },
+            material: materials.shared(color),
+            transform: Transform::from_translation(Vec3::new(pos[0], pos[1], pos[2])),
+            ..Default::default()
+        });
+        cmd.insert(Tile {
+            coord,
+            kind,
+            height,
+            color,
+        });
+
+        if kind == TileKind::Water {
+            cmd.insert(Water);
+        }
+    }
+
+    if chunks.is_empty() {
+        return;
+    }
+    // Every chunk shares one white material, which the vertex colors tint
+    let material = materials.assets.add(Color::WHITE.into());
+    for (key, tiles) in chunks {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(chunk_mesh(geometry::build_chunk_mesh(&tiles))),
+                material: material.clone(),
+                ..Default::default()
+            },
+            TileChunk(key),
+        ));
+    }
+}
+
+/// Chunked tiles that have moved, changed, or flooded since the last frame
+type ChangedChunkedTiles<'w, 's> = Query<
+    'w,
+    's,
+    Ref<'static, Tile>,
+    (
+        With<Chunked>,
+        Or<(Changed<Tile>, Changed<Transform>, Added<Water>)>,
+    ),
+>;
+
+/// Rebuild the mesh of every chunk with a tile that's changed, so edits, easing and flooding show on merged land.
+/// Chunks are built along with their tiles, so there's nothing to do for tiles that were only just spawned
+pub fn rebuild_chunks(
+    changed: ChangedChunkedTiles,
+    tiles: Query<(&Tile, &Transform, Has<Water>), With<Chunked>>,
+    chunks: Query<(&TileChunk, &Handle<Mesh>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let dirty: HashSet<_> = changed
+        .iter()
+        .filter(|tile| !tile.is_added())
+        .map(|tile| chunk_of(&tile.coord))
+        .collect();
+    if dirty.is_empty() {
+        return;
+    }
+    let mut contents: HashMap<_, Vec<_>> = HashMap::new();
+    for (tile, transform, is_water) in &tiles {
+        let key = chunk_of(&tile.coord);
+        if dirty.contains(&key) {
+            let color = chunked_color(tile, is_water);
+            contents
+                .entry(key)
+                .or_default()
+                .push((transform.translation.to_array(), color));
+        }
+    }
+    for (TileChunk(key), handle) in &chunks {
+        if let Some(tiles) = contents.get(key) {
+            meshes.insert(handle, chunk_mesh(geometry::build_chunk_mesh(tiles)));
+        }
+    }
+}
+
+/// Spawn the tiles from a background map generation, once it's finished
+pub fn finish_map_generation(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    generation: Option<ResMut<MapGeneration>>,
+    land: Res<LandMesh>,
+    water: Res<WaterMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: TileMaterials,
+) {
+    let Some(mut generation) = generation else {
+        return;
+    };
+    if let Some(tiles) = future::block_on(future::poll_once(&mut generation.0)) {
+        spawn_tiles(
+            &mut commands,
+            &config,
+            &land,
+            &water,
+            &mut meshes,
+            &mut materials,
+            tiles,
+        );
+        commands.remove_resource::<MapGeneration>();
+    }
+}
+
+/// A toon material that lights tiles in a few flat bands rather than a smooth ramp
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct CelMaterial {
+    #[uniform(0)]
+    pub color: Color,
+}
+
+impl Material for CelMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/cel.wgsl".into()
+    }
+}
+
+/// Swap newly spawned tiles over to a [CelMaterial] of the same color, if the map asks for cel shading
+pub fn apply_cel_shading(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    standard: Res<Assets<StandardMaterial>>,
+    mut cel: ResMut<Assets<CelMaterial>>,
+    tiles: Query<(Entity, &Handle<StandardMaterial>), Added<Tile>>,
+) {
+    if !config.cel_shading {
+        return;
+    }
+    for (entity, handle) in &tiles {
+        let color = standard
+            .get(handle)
+            .map_or(Color::WHITE, |material| material.base_color);
+        commands
+            .entity(entity)
+            .remove::<Handle<StandardMaterial>>()
+            .insert(cel.add(CelMaterial { color }));
+    }
+}
+
+/// The highest a generated tile stands, with hills at their tallest
+const MAX_TILE_HEIGHT: f32 = terrain::NOISE_HEIGHT_RANGE.1;
+
+/// The smallest bevel factor the tile mesh is built with, so the top face never shrinks away to nothing
+const MIN_BEVEL_FACTOR: f32 = 0.05;
+
+/// The shape of the mesh shared by every tile. Changing it rebuilds that mesh, so the bevel can be tuned while the
+/// map is up; merged chunks from [MapConfig::chunked] keep the shape they were built with.
+/// The mesh is always [geometry::DEFAULT_HEX_RADIUS] across, since that's the radius tiles are laid out and picked with
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct BevelConfig {
+    /// How much of the radius the flat top covers, with the rest sloping down to the edge. See [BevelConfig::factor]
+    pub factor: f32,
+    /// How far below the top the skirt hangs, hiding gaps next to lower tiles
+    pub skirt_depth: f32,
+}
+
+impl Default for BevelConfig {
+    fn default() -> Self {
+        BevelConfig {
+            factor: geometry::DEFAULT_BEVEL_FACTOR,
+            // The skirt only has to hide the gap down to the sea floor beside the tallest hill, with a little to spare
+            skirt_depth: MAX_TILE_HEIGHT + 1.,
+        }
+    }
+}
+
+impl BevelConfig {
+    /// The bevel factor to actually build with, kept within `MIN_BEVEL_FACTOR..=1`
+    pub fn factor(&self) -> f32 {
+        if self.factor.is_nan() {
+            return 1.;
+        }
+        self.factor.clamp(MIN_BEVEL_FACTOR, 1.)
     }
 }
 
 /// Generate a single hex mesh
-fn generate_hex_mesh() -> Mesh {
+fn generate_hex_mesh(bevel: &BevelConfig) -> Mesh {
     let mut pts: Vec<[f32; 3]> = vec![];
     let c = hex::HexCoord::new(0, 0);
-    geometry::bevel_hexagon_points(&mut pts, 1.0, 0.9, &c);
+    geometry::bevel_hexagon_points_with_skirt(
+        &mut pts,
+        geometry::DEFAULT_HEX_RADIUS,
+        bevel.factor(),
+        bevel.skirt_depth,
+        &c,
+    );
 
     let mut normals: Vec<[f32; 3]> = vec![];
     geometry::bevel_hexagon_normals(&mut normals);
 
     let mut uvs: Vec<[f32; 2]> = vec![];
-    for _ in 0..pts.len() {
-        uvs.push([0., 0.]);
-    }
+    geometry::bevel_hexagon_uvs(&mut uvs);
 
     let mut indices = vec![];
     geometry::bevel_hexagon_indices(&mut indices);
@@ -104,49 +818,4058 @@ fn generate_hex_mesh() -> Mesh {
 
 /* Supporting systems */
 
+/// How the camera speeds up and slows down as it's flown around with the keyboard
+#[derive(Resource)]
+pub struct CameraMovement {
+    /// The fastest the camera flies along each axis, in units per second
+    pub max_speed: f32,
+    /// How quickly the camera gets up to speed, and comes back to rest, in units per second per second
+    pub acceleration: f32,
+    /// Once the keys are let go, speeds below this count as stopped, so the camera doesn't creep along
+    pub deadzone: f32,
+    /// How fast the camera is flying right now, eased toward whatever the keys ask for
+    pub velocity: Vec3,
+    /// Once the camera comes to rest, settle it so it's looking straight at the middle of a tile
+    pub snap_to_tiles: bool,
+    /// How quickly the camera settles; the distance left shrinks by this fraction of itself per second (roughly)
+    pub snap_rate: f32,
+}
+
+impl Default for CameraMovement {
+    fn default() -> Self {
+        CameraMovement {
+            max_speed: 10.,
+            acceleration: 40.,
+            deadzone: 0.05,
+            velocity: Vec3::ZERO,
+            snap_to_tiles: false,
+            snap_rate: 6.,
+        }
+    }
+}
+
 /// Move the camera around with the keyboard
 pub fn keyboard_controls(
     input: Res<Input<KeyCode>>,
     time: Res<Time>,
-    mut query: Query<(&mut Transform, &Camera)>,
+    mut movement: ResMut<CameraMovement>,
+    mut query: Query<(&mut Transform, &Camera), Without<ReflectionCamera>>,
 ) {
     if let Some((mut transform, _camera)) = query.iter_mut().next() {
-        let speed = 10.;
         let forward = Vec3::new(1., 0., 0.);
         let left = Vec3::new(0., 0., -1.);
         let up = Vec3::new(0., 1., 0.);
-        let mut pos = transform.translation;
+        let mut heading = Vec3::ZERO;
         if input.pressed(KeyCode::W) {
-            pos += speed * forward * time.delta_seconds();
+            heading += forward;
         } else if input.pressed(KeyCode::S) {
-            pos -= speed * forward * time.delta_seconds();
+            heading -= forward;
         }
         if input.pressed(KeyCode::A) {
-            pos += speed * left * time.delta_seconds();
+            heading += left;
         } else if input.pressed(KeyCode::D) {
-            pos -= speed * left * time.delta_seconds();
+            heading -= left;
         }
         if input.pressed(KeyCode::Q) {
-            pos += speed * up * time.delta_seconds();
+            heading += up;
         } else if input.pressed(KeyCode::E) {
-            pos -= speed * up * time.delta_seconds();
+            heading -= up;
+        }
+
+        // Ease toward the speed the keys ask for, changing no faster than the acceleration allows
+        let target = heading * movement.max_speed;
+        let dt = time.delta_seconds();
+        let change = (target - movement.velocity).clamp_length_max(movement.acceleration * dt);
+        movement.velocity += change;
+        if heading == Vec3::ZERO && movement.velocity.length() < movement.deadzone {
+            movement.velocity = Vec3::ZERO;
+        }
+
+        transform.translation += movement.velocity * dt;
+    }
+}
+
+/// Toggle [CameraMovement::snap_to_tiles]
+pub fn toggle_camera_snap(input: Res<Input<KeyCode>>, mut movement: ResMut<CameraMovement>) {
+    if input.just_pressed(KeyCode::G) {
+        movement.snap_to_tiles = !movement.snap_to_tiles;
+    }
+}
+
+/// Once the camera has stopped, gently slide it sideways until the spot it's looking at is the middle of a tile
+pub fn snap_camera_to_tile(
+    time: Res<Time>,
+    movement: Res<CameraMovement>,
+    mut query: Query<&mut Transform, (With<Camera>, Without<ReflectionCamera>)>,
+) {
+    if !movement.snap_to_tiles || movement.velocity != Vec3::ZERO {
+        return;
+    }
+    for mut transform in &mut query {
+        let Some(ground) = ground_intersection(transform.translation, transform.forward()) else {
+            continue;
+        };
+        let c =
+            geometry::hex_at_point(MAP_LAYOUT, geometry::DEFAULT_HEX_RADIUS, &ground.to_array());
+        let [x, _, z] = geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &c,
+            &[0., 0., 0.],
+        );
+        let remaining = Vec3::new(x - ground.x, 0., z - ground.z);
+        if remaining.length() < 1e-3 {
+            // Close enough to finish off, rather than creeping closer forever
+            transform.translation += remaining;
+        } else {
+            let step = 1. - (-movement.snap_rate * time.delta_seconds()).exp();
+            transform.translation += remaining * step;
+        }
+    }
+}
+
+/// How the camera is steered
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    /// Fly around with the keyboard
+    #[default]
+    Fly,
+    /// Drag with the middle mouse button to swing around the spot the camera is looking at
+    Orbit,
+}
+
+/// Switch between [CameraMode]s with Tab
+pub fn toggle_camera_mode(
+    input: Res<Input<KeyCode>>,
+    mut mode: ResMut<CameraMode>,
+    mut movement: ResMut<CameraMovement>,
+) {
+    if input.just_pressed(KeyCode::Tab) {
+        *mode = match *mode {
+            CameraMode::Fly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Fly,
+        };
+        // Don't pick up coasting where we left off when coming back to flying
+        movement.velocity = Vec3::ZERO;
+    }
+}
+
+/// How the orbit camera responds to the mouse
+#[derive(Resource, Clone, Debug)]
+pub struct OrbitSettings {
+    /// How far the camera swings for each pixel dragged, in radians
+    pub sensitivity: f32,
+    /// The lowest and highest the camera goes, as an angle up from the ground. Keeping short of straight up stops the
+    /// camera flipping over as it passes the top
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+}
+
+impl Default for OrbitSettings {
+    fn default() -> Self {
+        OrbitSettings {
+            sensitivity: 0.005,
+            min_pitch: 0.1,
+            max_pitch: std::f32::consts::FRAC_PI_2 - 0.05,
         }
+    }
+}
+
+/// How far ahead the orbit camera swings around when it's looking up at the sky, rather than at the ground
+const SKY_FOCUS_DISTANCE: f32 = 10.;
+
+/// Where the camera at `transform` ends up after orbiting the spot it's looking at, swung round by `drag` pixels.
+/// If it's looking up at the sky, it orbits a spot a little way ahead instead
+fn orbit(transform: &Transform, drag: Vec2, settings: &OrbitSettings) -> Transform {
+    let forward = transform.forward();
+    let focus = ground_intersection(transform.translation, forward)
+        .unwrap_or(transform.translation + forward * SKY_FOCUS_DISTANCE);
+    let offset = transform.translation - focus;
+    let distance = offset.length();
+    let yaw = offset.z.atan2(offset.x) - drag.x * settings.sensitivity;
+    let pitch = ((offset.y / distance).asin() + drag.y * settings.sensitivity)
+        .clamp(settings.min_pitch, settings.max_pitch);
+    let direction = Vec3::new(
+        pitch.cos() * yaw.cos(),
+        pitch.sin(),
+        pitch.cos() * yaw.sin(),
+    );
+    Transform::from_translation(focus + direction * distance).looking_at(focus, Vec3::Y)
+}
+
+/// Swing the camera around with the middle mouse button
+pub fn orbit_controls(
+    buttons: Res<Input<MouseButton>>,
+    settings: Res<OrbitSettings>,
+    mut motion: EventReader<MouseMotion>,
+    mut query: Query<&mut Transform, (With<Camera>, Without<ReflectionCamera>)>,
+) {
+    let moved: Vec2 = motion.read().map(|m| m.delta).sum();
+    if !buttons.pressed(MouseButton::Middle) || moved == Vec2::ZERO {
+        return;
+    }
+    for mut transform in &mut query {
+        *transform = orbit(&transform, moved, &settings);
+    }
+}
+
+/// How close to and far from the ground the scroll wheel can take the camera, measured along its view
+#[derive(Resource, Clone, Debug)]
+pub struct ZoomLimits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for ZoomLimits {
+    fn default() -> Self {
+        ZoomLimits { min: 2., max: 100. }
+    }
+}
+
+/// The share of the distance to the ground covered by each line scrolled
+const ZOOM_PER_LINE: f32 = 0.1;
+
+/// Roughly how many pixels a trackpad scrolls for each line a mouse wheel would
+const PIXELS_PER_LINE: f32 = 20.;
+
+/// How far a scroll went, in lines whatever device sent it
+fn scroll_lines(event: &MouseWheel) -> f32 {
+    match event.unit {
+        MouseScrollUnit::Line => event.y,
+        MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+    }
+}
+
+/// Move the camera toward or away from the spot on the ground it's looking at, by `lines` scrolled. Nothing happens
+/// if it's looking up at the sky, since there's nothing to zoom toward
+fn zoom(transform: &Transform, lines: f32, limits: &ZoomLimits) -> Transform {
+    let forward = transform.forward();
+    let Some(ground) = ground_intersection(transform.translation, forward) else {
+        return *transform;
+    };
+    let distance = transform.translation.distance(ground);
+    let distance = (distance * (1. - ZOOM_PER_LINE).powf(lines)).clamp(limits.min, limits.max);
+    let mut zoomed = *transform;
+    zoomed.translation = ground - forward * distance;
+    zoomed
+}
+
+/// Zoom the camera with the scroll wheel, in either [CameraMode]
+pub fn camera_zoom(
+    limits: Res<ZoomLimits>,
+    mut wheel: EventReader<MouseWheel>,
+    mut query: Query<&mut Transform, (With<Camera>, Without<ReflectionCamera>)>,
+) {
+    // Trackpads send lots of little scrolls a frame, so add them all up
+    let lines: f32 = wheel.read().map(scroll_lines).sum();
+    if lines == 0. {
+        return;
+    }
+    for mut transform in &mut query {
+        *transform = zoom(&transform, lines, &limits);
+    }
+}
+
+/// A single tile of the map
+#[derive(Component)]
+pub struct Tile {
+    pub coord: hex::HexCoord,
+    pub kind: TileKind,
+    /// The height of the ground, which may be below the water
+    pub height: f32,
+    /// The color of the ground, when it's not underwater
+    pub color: Color,
+}
+
+/// Where to put a camera looking along `forward`, with a vertical field of view `fov`, so that the whole box
+/// between `min` and `max` is in view
+fn framing_position(min: Vec3, max: Vec3, forward: Vec3, fov: f32, aspect: f32) -> Vec3 {
+    // Fit a sphere around the box, and back away until the sphere fits in the narrower of the two fields of view
+    let center = (min + max) / 2.;
+    let radius = (max - min).length() / 2.;
+    let half_vertical = fov / 2.;
+    let half_horizontal = (half_vertical.tan() * aspect).atan();
+    let distance = radius / half_vertical.min(half_horizontal).sin();
+    center - forward.normalize() * distance
+}
 
-        transform.translation = pos;
+/// Move the camera back along its current view direction until the whole map is in view
+pub fn frame_map(tiles: Query<&Tile>, mut cameras: MainCamera<(&mut Transform, &Projection)>) {
+    let coords: Vec<_> = tiles.iter().map(|t| t.coord).collect();
+    let Some((min, max)) = geometry::region_aabb(MAP_LAYOUT, geometry::DEFAULT_HEX_RADIUS, &coords)
+    else {
+        return;
+    };
+    let top = tiles.iter().map(|t| t.height).fold(max[1], f32::max);
+    let (min, max) = (Vec3::from(min), Vec3::new(max[0], top, max[2]));
+    for (mut transform, projection) in &mut cameras {
+        if let Projection::Perspective(perspective) = projection {
+            let forward = transform.forward();
+            transform.translation =
+                framing_position(min, max, forward, perspective.fov, perspective.aspect_ratio);
+        }
     }
 }
 
 #[derive(Component)]
 pub struct Water;
-/// Ripple water tiles slightly
-pub fn water_ripple(time: Res<Time>, mut q: Query<&mut Transform, With<Water>>) {
+
+/// The height of the water's surface; any tile lower than this is underwater
+#[derive(Resource, Default)]
+pub struct WaterLevel(pub f32);
+
+/// The slow rise and fall of the water level
+#[derive(Resource)]
+pub struct Tide {
+    /// Seconds for the water to rise, fall, and come back again
+    pub period: f32,
+    /// How far above and below the resting level the water goes
+    pub amplitude: f32,
+}
+
+impl Default for Tide {
+    fn default() -> Self {
+        Tide {
+            period: 60.,
+            amplitude: 0.4,
+        }
+    }
+}
+
+impl Tide {
+    /// The water level `seconds` into the tide
+    pub fn level_at(&self, seconds: f32) -> f32 {
+        self.amplitude * (seconds * std::f32::consts::TAU / self.period).sin()
+    }
+}
+
+/// Drive the water level with the tide
+pub fn tide(time: Res<Time>, tide: Res<Tide>, mut level: ResMut<WaterLevel>) {
+    level.0 = tide.level_at(time.elapsed_seconds());
+}
+
+/// Every tile the water might rise over or drain from, with whether it's water right now
+type FloodableTiles<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Tile,
+        // Cel shaded tiles have no standard material, but still flood
+        Option<&'static mut Handle<StandardMaterial>>,
+        &'static mut Transform,
+        Has<Water>,
+    ),
+>;
+
+/// Flood tiles that have fallen below the water level, and drain the ones that have surfaced again
+pub fn flood_tiles(
+    mut commands: Commands,
+    level: Res<WaterLevel>,
+    mut materials: TileMaterials,
+    mut q: FloodableTiles,
+) {
+    for (entity, tile, mut handle, mut transform, is_water) in &mut q {
+        let underwater = tile.height < level.0;
+        if underwater == is_water {
+            continue;
+        }
+        if let Some(material) = handle.as_deref_mut().and_then(|h| materials.own(h)) {
+            material.base_color = if underwater {
+                kind_color(TileKind::Water)
+            } else {
+                tile.color
+            };
+        }
+        if underwater {
+            commands.entity(entity).insert(Water);
+        } else {
+            // Water ripples move the tile around, so put it back on the ground
+            transform.translation.y = tile.height;
+            commands.entity(entity).remove::<Water>();
+        }
+    }
+}
+
+/// How far above or below the water's surface the ripples lift the point at `x`, `z`, `time` seconds in
+fn ripple_offset(time: f32, x: f32, z: f32) -> f32 {
+    let ripple1 = (time / 2. + (x / 3.) + (z / 3.)).sin() * 0.1 - 0.05;
+    let ripple2 = (time + (x / 3.) - (z / 4.)).cos() * 0.1 - 0.05;
+    let ripple3 = (time * 2. + (x / 5.) - (z / 7.)).sin() * 0.1 - 0.05;
+    ripple1 + ripple2 + ripple3
+}
+
+/// Seas with fewer tiles than this ripple on one thread. In release builds `ripple_benchmark` puts rippling at about
+/// 0.02 microseconds a tile, and handing the work out to the task pool at around 5 microseconds, so even with only
+/// two threads sharing the work, splitting up a sea starts to pay off somewhere past 500 tiles
+const PARALLEL_RIPPLE_MIN_TILES: usize = 512;
+
+/// Ripple water tiles slightly.
+/// Each tile only depends on its own position, so big seas are split up across threads
+pub fn water_ripple(
+    time: Res<Time>,
+    level: Res<WaterLevel>,
+    mut q: Query<&mut Transform, With<Water>>,
+) {
+    let parallel = q.iter().len() >= PARALLEL_RIPPLE_MIN_TILES;
+    ripple_tiles(&time, &level, &mut q, parallel);
+}
+
+/// Move every tile in `q` to its place in the ripples, either on this thread or across the task pool
+fn ripple_tiles(
+    time: &Time,
+    level: &WaterLevel,
+    q: &mut Query<&mut Transform, With<Water>>,
+    parallel: bool,
+) {
     let time = time.elapsed_seconds();
-    for mut t in &mut q {
+    let ripple = |mut t: Mut<Transform>| {
         let (x, z) = (t.translation.x, t.translation.z);
+        t.translation.y = level.0 + ripple_offset(time, x, z);
+    };
+    if parallel {
+        q.par_iter_mut().for_each(ripple);
+    } else {
+        q.iter_mut().for_each(ripple);
+    }
+}
+
+/// Step through bevel factors with B, from the default down to almost nothing, then back round to no bevel at all
+pub fn adjust_bevel(input: Res<Input<KeyCode>>, mut bevel: ResMut<BevelConfig>) {
+    if input.just_pressed(KeyCode::B) {
+        let factor = bevel.factor() - 0.1;
+        bevel.factor = if factor < MIN_BEVEL_FACTOR {
+            1.
+        } else {
+            factor
+        };
+    }
+}
+
+/// Rebuild the land and water meshes with the current [BevelConfig]
+pub fn rebuild_hex_meshes(
+    bevel: Res<BevelConfig>,
+    land: Res<LandMesh>,
+    water: Res<WaterMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    // Water's UVs are put back by `scroll_water`, and they don't depend on the bevel anyway
+    meshes.insert(land.0.clone(), generate_hex_mesh(&bevel));
+    meshes.insert(water.mesh.clone(), generate_hex_mesh(&bevel));
+}
+
+/// The mesh shared by all water tiles, along with its unscrolled UVs
+#[derive(Resource)]
+pub struct WaterMesh {
+    pub mesh: Handle<Mesh>,
+    pub uvs: Vec<[f32; 2]>,
+}
+
+/// How quickly the water texture scrolls, and how far it has scrolled so far
+#[derive(Resource)]
+pub struct WaterScroll {
+    pub speed: Vec2,
+    pub offset: Vec2,
+}
+
+impl Default for WaterScroll {
+    fn default() -> Self {
+        WaterScroll {
+            speed: Vec2::new(0.05, 0.02),
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+impl WaterScroll {
+    /// Move the offset along by `seconds` worth of scrolling, wrapping so it stays within a single texture repeat
+    pub fn advance(&mut self, seconds: f32) {
+        self.offset = (self.offset + self.speed * seconds).fract();
+    }
+}
+
+/// Scroll the UVs of the water mesh, so the caustics on the water drift over time
+pub fn scroll_water(
+    time: Res<Time>,
+    mut scroll: ResMut<WaterScroll>,
+    water: Res<WaterMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    scroll.advance(time.delta_seconds());
+    let offset = scroll.offset;
+    let Some(mesh) = meshes.get_mut(&water.mesh) else {
+        return;
+    };
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+        for (uv, [u, v]) in uvs.iter_mut().zip(&water.uvs) {
+            *uv = [u + offset.x, v + offset.y];
+        }
+    }
+}
+
+/// A second camera that watches the scene from beneath the water, for [ReflectiveWater] to show
+#[derive(Component)]
+pub struct ReflectionCamera;
+
+/// The camera the player looks through, leaving out the [ReflectionCamera]
+type MainCamera<'w, 's, D> = Query<'w, 's, D, (With<Camera3d>, Without<ReflectionCamera>)>;
+
+/// The sheet of water the reflection is shown on, which rises and falls with the [WaterLevel]
+#[derive(Component)]
+pub struct ReflectionSurface;
+
+/// A see-through water surface showing what the [ReflectionCamera] sees, lined up by screen position
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct ReflectiveWater {
+    #[uniform(0)]
+    pub tint: Color,
+    #[texture(1)]
+    #[sampler(2)]
+    pub reflection: Handle<Image>,
+}
+
+impl Material for ReflectiveWater {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/water_reflection.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+}
+
+/// How far the reflective surface reaches, comfortably past the edges of the map
+const REFLECTION_SURFACE_SIZE: f32 = 200.;
+
+/// Where a camera at `camera` would be if it were mirrored in water at height `level`, so that it sees the reflection.
+/// It stays upright rather than upside down, so its picture comes out flipped vertically
+fn mirror_across_water(camera: &Transform, level: f32) -> Transform {
+    let mut position = camera.translation;
+    position.y = 2. * level - position.y;
+    let forward = camera.forward() * Vec3::new(1., -1., 1.);
+    Transform::from_translation(position).looking_to(forward, Vec3::Y)
+}
+
+/// If the map asks for reflections, set up a camera rendering into a texture, and a surface to show it on
+fn setup_reflections(
+    mut commands: Commands,
+    config: Res<MapConfig>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ReflectiveWater>>,
+) {
+    if !config.reflections {
+        return;
+    }
+    // Match the window, so the reflection has the same shape as the screen it's lined up with
+    let size = windows.get_single().map_or(
+        Extent3d {
+            width: 1280,
+            height: 720,
+            ..Default::default()
+        },
+        |window| Extent3d {
+            width: window.physical_width().max(1),
+            height: window.physical_height().max(1),
+            ..Default::default()
+        },
+    );
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("reflection"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..Default::default()
+    };
+    image.resize(size);
+    let reflection = images.add(image);
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Draw the reflection before the main camera needs it
+                order: -1,
+                target: RenderTarget::Image(reflection.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        UiCameraConfig { show_ui: false },
+        ReflectionCamera,
+    ));
+    commands.spawn((
+        MaterialMeshBundle {
+            mesh: meshes.add(shape::Plane::from_size(REFLECTION_SURFACE_SIZE).into()),
+            material: materials.add(ReflectiveWater {
+                tint: kind_color(TileKind::Water).with_a(0.7),
+                reflection,
+            }),
+            ..Default::default()
+        },
+        ReflectionSurface,
+    ));
+}
+
+/// Keep the reflection camera mirroring the main camera, and the surface at the water level
+pub fn follow_reflection(
+    level: Res<WaterLevel>,
+    main: Query<&Transform, (With<Camera3d>, Without<ReflectionCamera>)>,
+    mut reflection: Query<&mut Transform, (With<ReflectionCamera>, Without<ReflectionSurface>)>,
+    mut surface: Query<&mut Transform, (With<ReflectionSurface>, Without<Camera3d>)>,
+) {
+    for mut transform in &mut surface {
+        transform.translation.y = level.0;
+    }
+    let Ok(camera) = main.get_single() else {
+        return;
+    };
+    for mut transform in &mut reflection {
+        *transform = mirror_across_water(camera, level.0);
+    }
+}
+
+/// Briefly flash a tile's material towards `color`, and back again, over `duration` seconds
+#[derive(Component)]
+pub struct TileFlash {
+    pub color: Color,
+    pub duration: f32,
+    pub elapsed: f32,
+    /// The material's color from before the flash, so we can put it back exactly
+    original: Option<Color>,
+}
+
+impl TileFlash {
+    pub fn new(color: Color, duration: f32) -> Self {
+        TileFlash {
+            color,
+            duration,
+            elapsed: 0.,
+            original: None,
+        }
+    }
+}
+
+/// Blend between two colors, in the same space the material stores them in
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let (a, b) = (from.as_rgba_f32(), to.as_rgba_f32());
+    let mix = |i: usize| a[i] + (b[i] - a[i]) * t;
+    Color::rgba(mix(0), mix(1), mix(2), mix(3))
+}
+
+/// Drive any active tile flashes, restoring the original color once they finish
+pub fn tile_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut materials: TileMaterials,
+    mut q: Query<(Entity, &mut TileFlash, &mut Handle<StandardMaterial>)>,
+) {
+    for (entity, mut flash, mut handle) in &mut q {
+        let Some(material) = materials.own(&mut handle) else {
+            continue;
+        };
+        let original = *flash.original.get_or_insert(material.base_color);
+        flash.elapsed += time.delta_seconds();
+        if flash.elapsed >= flash.duration {
+            material.base_color = original;
+            commands.entity(entity).remove::<TileFlash>();
+        } else {
+            // Ramp towards the flash color for the first half, and back for the second
+            let t = 1. - (2. * flash.elapsed / flash.duration - 1.).abs();
+            material.base_color = lerp_color(original, flash.color, t);
+        }
+    }
+}
+
+/// Flash a random tile when F is pressed, to show off [TileFlash]
+pub fn flash_random_tile(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    q: Query<Entity, (With<Handle<StandardMaterial>>, Without<TileFlash>)>,
+) {
+    if input.just_pressed(KeyCode::F) {
+        if let Some(entity) = q.iter().choose(&mut rand::thread_rng()) {
+            commands
+                .entity(entity)
+                .insert(TileFlash::new(Color::WHITE, 0.5));
+        }
+    }
+}
+
+/// Whether to draw the coordinate axes at the origin, as a teaching aid
+#[derive(Resource, Default)]
+pub struct ShowAxes(pub bool);
+
+/// Toggle the coordinate axes with X
+pub fn toggle_axes(input: Res<Input<KeyCode>>, mut show: ResMut<ShowAxes>) {
+    if input.just_pressed(KeyCode::X) {
+        show.0 = !show.0;
+    }
+}
+
+/// The arrows to draw for the +q, +r, and +s axes, colored red, green, and blue respectively
+fn axis_arrows(length: f32) -> [(Vec3, Color); 3] {
+    let [q, r, s] = geometry::axis_vectors(geometry::DEFAULT_HEX_RADIUS)
+        .map(|v| Vec3::from(v).normalize() * length);
+    [(q, Color::RED), (r, Color::GREEN), (s, Color::BLUE)]
+}
+
+/// Where the axis arrows start, hovering above the origin tile
+const AXES_ORIGIN: Vec3 = Vec3::new(0., 3., 0.);
+
+/// How long each axis arrow is
+const AXIS_LENGTH: f32 = 3.;
+
+/// Draw an arrow along each coordinate axis, hovering above the origin tile
+pub fn draw_axes(show: Res<ShowAxes>, mut gizmos: Gizmos) {
+    if !show.0 {
+        return;
+    }
+    for (dir, color) in axis_arrows(AXIS_LENGTH) {
+        let tip = AXES_ORIGIN + dir;
+        gizmos.line(AXES_ORIGIN, tip, color);
+        // Two short strokes back from the tip make the arrow head
+        let back = dir.normalize() * 0.3;
+        let side = dir.cross(Vec3::Y).normalize() * 0.15;
+        gizmos.line(tip, tip - back + side, color);
+        gizmos.line(tip, tip - back - side, color);
+    }
+}
+
+/// A label naming one of the axes that [draw_axes] draws, by index into [axis_arrows]
+#[derive(Component)]
+pub struct AxisLabel(pub usize);
+
+fn spawn_axis_labels(mut commands: Commands) {
+    for (i, ((_, color), name)) in axis_arrows(AXIS_LENGTH)
+        .into_iter()
+        .zip(["+q", "+r", "+s"])
+        .enumerate()
+    {
+        commands.spawn((
+            TextBundle {
+                visibility: Visibility::Hidden,
+                ..TextBundle::from_section(
+                    name,
+                    TextStyle {
+                        font_size: 20.,
+                        color,
+                        ..Default::default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                })
+            },
+            AxisLabel(i),
+        ));
+    }
+}
+
+/// Keep each axis label on screen just past the tip of its arrow, showing and hiding them along with the arrows
+pub fn place_axis_labels(
+    show: Res<ShowAxes>,
+    cameras: MainCamera<(&Camera, &GlobalTransform)>,
+    mut labels: Query<(&AxisLabel, &mut Style, &mut Visibility)>,
+) {
+    let arrows = axis_arrows(AXIS_LENGTH);
+    let camera = cameras.get_single().ok().filter(|_| show.0);
+    for (label, mut style, mut visibility) in &mut labels {
+        let (dir, _) = arrows[label.0];
+        // A little past the tip, so the label doesn't sit on top of the arrow head
+        let tip = AXES_ORIGIN + dir * 1.15;
+        match camera.and_then(|(camera, transform)| camera.world_to_viewport(transform, tip)) {
+            Some(pos) => {
+                style.left = Val::Px(pos.x);
+                style.top = Val::Px(pos.y);
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// The tile under the cursor, if any
+#[derive(Resource, Default, PartialEq, Debug)]
+pub struct HoveredHex(pub Option<hex::HexCoord>);
+
+/// The glow added to tiles that share the hovered tile's kind
+const KIND_HIGHLIGHT: Color = Color::rgb(0.25, 0.25, 0.25);
+
+/// Highlight every tile of the same kind as the hovered one, so it's easy to see where each kind of terrain is
+pub fn highlight_hovered_kind(
+    hovered: Res<HoveredHex>,
+    mut highlighted: Local<Option<TileKind>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    tiles: Query<(&Tile, &Handle<StandardMaterial>)>,
+) {
+    if !hovered.is_changed() {
+        return;
+    }
+    let kind = hovered
+        .0
+        .and_then(|c| tiles.iter().find(|(tile, _)| tile.coord == c))
+        .map(|(tile, _)| tile.kind);
+    if kind == *highlighted {
+        return;
+    }
+    // We use the emissive color, so we don't fight with anything else that recolors tiles
+    for (tile, handle) in &tiles {
+        if let Some(material) = materials.get_mut(handle) {
+            material.emissive = if Some(tile.kind) == kind {
+                KIND_HIGHLIGHT
+            } else {
+                Color::BLACK
+            };
+        }
+    }
+    *highlighted = kind;
+}
+
+/// Where a ray hits the ground plane, if it does at all
+fn ground_intersection(origin: Vec3, direction: Vec3) -> Option<Vec3> {
+    if direction.y.abs() < f32::EPSILON {
+        // Parallel to the ground, so it never hits
+        return None;
+    }
+    let distance = -origin.y / direction.y;
+    if distance < 0. {
+        // Pointing away from the ground
+        return None;
+    }
+    Some(origin + direction * distance)
+}
+
+/// Find the tile under the cursor, by casting a ray from the camera onto the ground
+fn cursor_to_hex(
+    camera: &Camera,
+    transform: &GlobalTransform,
+    cursor: Vec2,
+) -> Option<hex::HexCoord> {
+    let ray = camera.viewport_to_world(transform, cursor)?;
+    let hit = ground_intersection(ray.origin, ray.direction)?;
+    Some(geometry::hex_at_point(
+        MAP_LAYOUT,
+        geometry::DEFAULT_HEX_RADIUS,
+        &hit.to_array(),
+    ))
+}
+
+/// Keep [HoveredHex] up to date with the tile under the cursor.
+/// There's no tile if the cursor is outside the window, or it's pointing at the sky rather than the ground
+pub fn picking(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: MainCamera<(&Camera, &GlobalTransform)>,
+    mut hovered: ResMut<HoveredHex>,
+) {
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let c = cursor.and_then(|cursor| {
+        let (camera, transform) = cameras.get_single().ok()?;
+        cursor_to_hex(camera, transform, cursor)
+    });
+    // Only count as a change when the tile does, so systems watching for a new hover don't run every frame
+    hovered.set_if_neq(HoveredHex(c));
+}
+
+/// A label that follows the mouse around, showing the coordinate underneath it
+#[derive(Component)]
+pub struct CoordinateReadout;
+
+fn spawn_coordinate_readout(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            ..Default::default()
+        }),
+        CoordinateReadout,
+    ));
+}
+
+/// Keep the coordinate readout next to the cursor, showing the [HoveredHex] that [picking] found, so the label always
+/// agrees with what every other system thinks is hovered. It's hidden when the cursor isn't over the ground
+pub fn coordinate_readout(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    hovered: Res<HoveredHex>,
+    mut readout: Query<(&mut Text, &mut Style, &mut Visibility), With<CoordinateReadout>>,
+) {
+    let Ok((mut text, mut style, mut visibility)) = readout.get_single_mut() else {
+        return;
+    };
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    match cursor.zip(hovered.0) {
+        Some((cursor, c)) => {
+            text.sections[0].value = c.to_string();
+            style.left = Val::Px(cursor.x + 16.);
+            style.top = Val::Px(cursor.y + 16.);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+/// A single reversible change to the map, with the state of each affected tile before and after
+#[derive(Clone, Debug)]
+pub struct TileEdit {
+    pub tiles: Vec<(hex::HexCoord, TileData, TileData)>,
+}
+
+/// Edits that can be undone, and edits that were undone and can be redone
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo: Vec<TileEdit>,
+    redo: Vec<TileEdit>,
+}
+
+impl EditHistory {
+    /// Remember a new edit; anything previously undone can no longer be redone
+    pub fn record(&mut self, edit: TileEdit) {
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Take the most recent edit, returning the tile states that reverse it
+    pub fn undo(&mut self) -> Option<Vec<(hex::HexCoord, TileData)>> {
+        let edit = self.undo.pop()?;
+        let states = edit.tiles.iter().map(|(c, before, _)| (*c, *before));
+        let states = states.collect();
+        self.redo.push(edit);
+        Some(states)
+    }
+
+    /// Take the most recently undone edit, returning the tile states that reapply it
+    pub fn redo(&mut self) -> Option<Vec<(hex::HexCoord, TileData)>> {
+        let edit = self.redo.pop()?;
+        let states = edit.tiles.iter().map(|(c, _, after)| (*c, *after));
+        let states = states.collect();
+        self.undo.push(edit);
+        Some(states)
+    }
+}
+
+/// Every tile a terrain edit might touch, with what it needs to change: its data, where it sits now, its material,
+/// and whether it's water, which keeps its own look
+type EditableTiles<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Tile,
+        &'static Transform,
+        // Cel shaded tiles have no standard material, but can still be edited
+        Option<&'static mut Handle<StandardMaterial>>,
+        Has<Water>,
+    ),
+>;
+
+/// Set tiles to a new state, updating how they look and easing them to their new height
+fn apply_tile_states(
+    commands: &mut Commands,
+    config: &MapConfig,
+    states: &[(hex::HexCoord, TileData)],
+    tiles: &mut EditableTiles,
+    materials: &mut TileMaterials,
+) {
+    for (entity, mut tile, transform, mut handle, is_water) in tiles.iter_mut() {
+        let Some((_, data)) = states.iter().find(|(c, _)| *c == tile.coord) else {
+            continue;
+        };
+        tile.kind = data.kind;
+        tile.height = data.height;
+        tile.color = tile_color(config, data.kind, &tile.coord);
+        // Water keeps its water color and ripple, until `flood_tiles` notices it's dried out
+        if !is_water {
+            commands
+                .entity(entity)
+                .insert(HeightTransition::new(transform.translation.y, data.height));
+            if let Some(material) = handle.as_deref_mut().and_then(|h| materials.own(h)) {
+                material.base_color = tile.color;
+            }
+        }
+    }
+}
+
+/// Raise or lower the hovered tile with PageUp and PageDown, or repaint it with 1 through 4
+pub fn edit_terrain(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    hovered: Res<HoveredHex>,
+    config: Res<MapConfig>,
+    mut history: ResMut<EditHistory>,
+    mut materials: TileMaterials,
+    mut tiles: EditableTiles,
+) {
+    let Some(coord) = hovered.0 else {
+        return;
+    };
+    let Some((_, tile, ..)) = tiles.iter().find(|(_, tile, ..)| tile.coord == coord) else {
+        return;
+    };
+    let before = TileData {
+        kind: tile.kind,
+        height: tile.height,
+    };
+    let mut after = before;
+    if input.just_pressed(KeyCode::PageUp) {
+        after.height += 0.25;
+    } else if input.just_pressed(KeyCode::PageDown) {
+        after.height -= 0.25;
+    } else if input.just_pressed(KeyCode::Key1) {
+        after.kind = TileKind::Water;
+    } else if input.just_pressed(KeyCode::Key2) {
+        after.kind = TileKind::Grass;
+    } else if input.just_pressed(KeyCode::Key3) {
+        after.kind = TileKind::Forest;
+    } else if input.just_pressed(KeyCode::Key4) {
+        after.kind = TileKind::Hills;
+    } else {
+        return;
+    }
+    history.record(TileEdit {
+        tiles: vec![(coord, before, after)],
+    });
+    apply_tile_states(
+        &mut commands,
+        &config,
+        &[(coord, after)],
+        &mut tiles,
+        &mut materials,
+    );
+}
+
+/// Undo the last edit with Ctrl+Z, and redo it with Ctrl+Y
+pub fn undo_redo(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    config: Res<MapConfig>,
+    mut history: ResMut<EditHistory>,
+    mut materials: TileMaterials,
+    mut tiles: EditableTiles,
+) {
+    if !input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let states = if input.just_pressed(KeyCode::Z) {
+        history.undo()
+    } else if input.just_pressed(KeyCode::Y) {
+        history.redo()
+    } else {
+        None
+    };
+    if let Some(states) = states {
+        apply_tile_states(&mut commands, &config, &states, &mut tiles, &mut materials);
+    }
+}
+
+/// Eases a tile from one height to another, rather than having it snap
+#[derive(Component)]
+pub struct HeightTransition {
+    pub from: f32,
+    pub to: f32,
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+impl HeightTransition {
+    pub fn new(from: f32, to: f32) -> Self {
+        HeightTransition {
+            from,
+            to,
+            duration: 0.3,
+            elapsed: 0.,
+        }
+    }
+}
+
+/// Move tiles along their height transitions, dropping each one once it arrives
+pub fn animate_heights(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q: Query<(Entity, &mut HeightTransition, &mut Transform, Has<Water>)>,
+) {
+    for (entity, mut transition, mut transform, is_water) in &mut q {
+        // The water ripple is in charge of flooded tiles, and `flood_tiles` puts them back when they dry out
+        if is_water {
+            commands.entity(entity).remove::<HeightTransition>();
+            continue;
+        }
+        transition.elapsed += time.delta_seconds();
+        let progress = (transition.elapsed / transition.duration).min(1.);
+        // Smoothstep, so the tile eases in and out of the motion
+        let eased = progress * progress * (3. - 2. * progress);
+        transform.translation.y = transition.from + (transition.to - transition.from) * eased;
+        if progress >= 1. {
+            commands.entity(entity).remove::<HeightTransition>();
+        }
+    }
+}
+
+/// How far the bobbing wave lags behind for each step away from the origin, in radians
+const BOB_PHASE_PER_RING: f32 = 0.5;
+
+/// A gentle wave that bobs land tiles up and down, rippling outward from the origin.
+/// Set the amplitude to 0 to hold them still
+#[derive(Resource)]
+pub struct IdleBob {
+    pub amplitude: f32,
+    /// How quickly each tile bobs, in radians per second
+    pub speed: f32,
+}
+
+impl Default for IdleBob {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.05,
+            speed: 2.,
+        }
+    }
+}
+
+impl IdleBob {
+    /// How far behind the origin the wave is at `c`
+    pub fn phase(c: &hex::HexCoord) -> f32 {
+        c.distance(&hex::HexCoord::origin()) as f32 * BOB_PHASE_PER_RING
+    }
+
+    /// How far above its base height the tile at `c` is, `seconds` into the wave
+    pub fn offset(&self, seconds: f32, c: &hex::HexCoord) -> f32 {
+        self.amplitude * (seconds * self.speed - Self::phase(c)).sin()
+    }
+}
+
+/// Land tiles that aren't already easing to a new height, and aren't merged into a chunk
+type BobbingTiles<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Tile, &'static mut Transform),
+    (Without<Water>, Without<HeightTransition>, Without<Chunked>),
+>;
+
+/// Bob land tiles around their base height. Water has its own ripple, and tiles mid-edit finish easing first.
+/// Chunked tiles hold still, or every chunk would be rebuilt every frame
+pub fn idle_bob(time: Res<Time>, bob: Res<IdleBob>, mut tiles: BobbingTiles) {
+    let seconds = time.elapsed_seconds();
+    for (tile, mut transform) in &mut tiles {
+        transform.translation.y = tile.height + bob.offset(seconds, &tile.coord);
+    }
+}
+
+/// The tile that was last clicked on, if any
+#[derive(Resource, Default)]
+pub struct SelectedHex(pub Option<hex::HexCoord>);
+
+/// Sent whenever a tile is clicked, so gameplay can react to clicks without watching the mouse itself
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileClicked {
+    pub coord: hex::HexCoord,
+    pub button: MouseButton,
+}
+
+/// Send a [TileClicked] for each mouse button pressed this frame while a tile is under the cursor
+pub fn emit_tile_clicks(
+    mouse: Res<Input<MouseButton>>,
+    hovered: Res<HoveredHex>,
+    mut clicks: EventWriter<TileClicked>,
+) {
+    let Some(coord) = hovered.0 else {
+        return;
+    };
+    for button in mouse.get_just_pressed() {
+        clicks.send(TileClicked {
+            coord,
+            button: *button,
+        });
+    }
+}
+
+/// A group of tiles picked out together by shift-clicking
+#[derive(Resource, Default)]
+pub struct SelectedRegion(pub HashSet<hex::HexCoord>);
+
+/// Select whichever tile is under the cursor when the left mouse button is clicked, starting a new [SelectedRegion]
+/// with just that tile. Holding shift adds the tile to the region instead, or takes it back out if it's already there
+pub fn select_hovered(
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    hovered: Res<HoveredHex>,
+    mut selected: ResMut<SelectedHex>,
+    mut region: ResMut<SelectedRegion>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        if let Some(c) = hovered.0 {
+            if !region.0.remove(&c) {
+                region.0.insert(c);
+            }
+        }
+    } else {
+        selected.0 = hovered.0;
+        region.0 = hovered.0.into_iter().collect();
+    }
+}
+
+/// Outline every tile in the [SelectedRegion], in the same color as the selection ring
+pub fn draw_selected_region(
+    mut gizmos: Gizmos,
+    region: Res<SelectedRegion>,
+    style: Res<BorderStyle>,
+    tiles: Query<&Tile>,
+) {
+    for tile in tiles.iter().filter(|tile| region.0.contains(&tile.coord)) {
+        outline_tile(&mut gizmos, tile, style.color);
+    }
+}
+
+/// The glowing outline around the selected tile
+#[derive(Component)]
+pub struct SelectionRing;
+
+/// How the outline around the selected tile looks; changing it rebuilds the outline live
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct BorderStyle {
+    /// How far the outline reaches in from the tile's edge
+    pub thickness: f32,
+    pub color: Color,
+    /// How far above the tile the outline floats, to avoid z-fighting
+    pub height_offset: f32,
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        Self {
+            thickness: 0.12,
+            color: Color::rgb(1.0, 0.85, 0.3),
+            height_offset: 0.02,
+        }
+    }
+}
+
+/// Generate the outline mesh for the selected tile
+fn generate_selection_ring_mesh(style: &BorderStyle) -> Mesh {
+    let mut pts: Vec<[f32; 3]> = vec![];
+    let c = hex::HexCoord::origin();
+    geometry::selection_ring_points(
+        &mut pts,
+        geometry::DEFAULT_HEX_RADIUS,
+        style.thickness,
+        0.08,
+        &c,
+    );
+
+    let mut normals: Vec<[f32; 3]> = vec![];
+    geometry::selection_ring_normals(&mut normals);
+
+    let uvs = vec![[0., 0.]; pts.len()];
+
+    let mut indices = vec![];
+    geometry::selection_ring_indices(&mut indices);
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, pts);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+/// A bright unlit material, so the outline reads clearly even in shadow
+fn glow_material(glow: Color) -> StandardMaterial {
+    StandardMaterial {
+        base_color: glow,
+        emissive: glow,
+        unlit: true,
+        ..Default::default()
+    }
+}
+
+fn spawn_selection_ring(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    style: Res<BorderStyle>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(generate_selection_ring_mesh(&style)),
+            material: materials.add(glow_material(style.color)),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        SelectionRing,
+    ));
+}
+
+/// Keep the outline sitting just above the selected tile, following it as it moves
+pub fn place_selection_ring(
+    selected: Res<SelectedHex>,
+    style: Res<BorderStyle>,
+    tiles: Query<(&Tile, &Transform), Without<SelectionRing>>,
+    mut ring: Query<(&mut Transform, &mut Visibility), With<SelectionRing>>,
+) {
+    let Ok((mut ring_transform, mut visibility)) = ring.get_single_mut() else {
+        return;
+    };
+    let tile = selected
+        .0
+        .and_then(|c| tiles.iter().find(|(tile, _)| tile.coord == c));
+    match tile {
+        Some((_, transform)) => {
+            ring_transform.translation = transform.translation + Vec3::Y * style.height_offset;
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+/// Rebuild the outline's mesh and material to match the current [BorderStyle]
+pub fn restyle_selection_ring(
+    style: Res<BorderStyle>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    ring: Query<(&Handle<Mesh>, &Handle<StandardMaterial>), With<SelectionRing>>,
+) {
+    for (mesh, material) in &ring {
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = generate_selection_ring_mesh(&style);
+        }
+        if let Some(material) = materials.get_mut(material) {
+            *material = glow_material(style.color);
+        }
+    }
+}
+
+/// Things that can be gathered from a tile
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TileResource {
+    Wood,
+    Ore,
+}
+
+/// Which resource, if any, a kind of tile provides
+fn resource_for(kind: TileKind) -> Option<TileResource> {
+    match kind {
+        TileKind::Forest => Some(TileResource::Wood),
+        TileKind::Hills => Some(TileResource::Ore),
+        TileKind::Water | TileKind::Grass => None,
+    }
+}
+
+/// Marks the icon floating above a resource-bearing tile
+#[derive(Component)]
+pub struct ResourceIcon(pub TileResource);
+
+/// The shared meshes and materials for each resource icon
+#[derive(Resource)]
+pub struct ResourceIcons {
+    pub wood: (Handle<Mesh>, Handle<StandardMaterial>),
+    pub ore: (Handle<Mesh>, Handle<StandardMaterial>),
+}
+
+fn setup_resource_icons(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(ResourceIcons {
+        wood: (
+            meshes.add(shape::Box::new(0.15, 0.4, 0.15).into()),
+            materials.add(Color::rgb(0.45, 0.3, 0.15).into()),
+        ),
+        ore: (
+            meshes.add(
+                shape::UVSphere {
+                    radius: 0.15,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            materials.add(Color::rgb(0.6, 0.6, 0.65).into()),
+        ),
+    });
+}
+
+/// How far above a tile its resource icon floats
+const ICON_HEIGHT: f32 = 0.5;
+
+/// How far from the camera small details stay visible, when [RenderQuality::lod] is on
+const DETAIL_DISTANCE: f32 = 25.;
+
+/// Hide resource icons that are too far from the camera to make out, if the render quality calls for it
+pub fn cull_distant_details(
+    quality: Res<RenderQuality>,
+    camera: Query<&GlobalTransform, (With<Camera>, Without<ReflectionCamera>)>,
+    mut icons: Query<(&GlobalTransform, &mut Visibility), With<ResourceIcon>>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (transform, mut visibility) in &mut icons {
+        let far = transform.translation().distance(camera.translation()) > DETAIL_DISTANCE;
+        let wanted = if quality.lod() && far {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+/// Keep an icon above every tile that has a resource, swapping it out when the tile changes
+pub fn spawn_resource_icons(
+    mut commands: Commands,
+    icons: Res<ResourceIcons>,
+    tiles: Query<(Entity, &Tile, Option<&Children>), Changed<Tile>>,
+    existing: Query<(), With<ResourceIcon>>,
+) {
+    for (entity, tile, children) in &tiles {
+        for child in children.into_iter().flatten() {
+            if existing.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        let Some(resource) = resource_for(tile.kind) else {
+            continue;
+        };
+        let (mesh, material) = match resource {
+            TileResource::Wood => icons.wood.clone(),
+            TileResource::Ore => icons.ore.clone(),
+        };
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform: Transform::from_xyz(0., ICON_HEIGHT, 0.),
+                    ..Default::default()
+                },
+                ResourceIcon(resource),
+            ));
+        });
+    }
+}
+
+/// A game piece standing on a tile
+#[derive(Component)]
+pub struct Token {
+    pub coord: hex::HexCoord,
+}
+
+/// The tiles a token still has to walk through, in order, ending at its destination
+#[derive(Component)]
+pub struct MoveAlongPath {
+    pub path: Vec<hex::HexCoord>,
+    pub step: Timer,
+}
+
+impl MoveAlongPath {
+    pub fn new(path: Vec<hex::HexCoord>) -> Self {
+        Self {
+            path,
+            step: Timer::from_seconds(0.25, TimerMode::Repeating),
+        }
+    }
+}
+
+/// How high above its tile a token stands
+const TOKEN_HEIGHT: f32 = 0.45;
+
+/// Once the map is in, stand a token on the land tile nearest the middle
+fn spawn_token(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    tiles: Query<&Tile, Added<Tile>>,
+    tokens: Query<(), With<Token>>,
+) {
+    if !tokens.is_empty() {
+        return;
+    }
+    let origin = hex::HexCoord::origin();
+    let Some(start) = tiles
+        .iter()
+        .filter(|tile| tile.kind != TileKind::Water)
+        .min_by_key(|tile| tile.coord.distance(&origin))
+    else {
+        return;
+    };
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(
+                shape::Capsule {
+                    radius: 0.2,
+                    depth: 0.4,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            material: materials.add(Color::rgb(0.8, 0.2, 0.2).into()),
+            ..Default::default()
+        },
+        Token { coord: start.coord },
+    ));
+}
+
+/// Right click a tile to walk the token on the selected tile there, going around water.
+/// Clicking the water heads for the nearest dry land instead.
+/// The selection follows the token to its destination, so it can be sent on again straight away
+pub fn click_to_move(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    hovered: Res<HoveredHex>,
+    mut selected: ResMut<SelectedHex>,
+    tiles: Query<&Tile>,
+    tokens: Query<(Entity, &Token, Option<&MoveAlongPath>)>,
+) {
+    if !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let (Some(target), Some(current)) = (hovered.0, selected.0) else {
+        return;
+    };
+    let destination = |token: &Token, movement: Option<&MoveAlongPath>| {
+        movement
+            .and_then(|m| m.path.last().copied())
+            .unwrap_or(token.coord)
+    };
+    let Some((entity, token, _)) = tokens
+        .iter()
+        .find(|(_, token, movement)| destination(token, *movement) == current)
+    else {
+        return;
+    };
+    let passable: HashMap<_, _> = tiles
+        .iter()
+        .map(|tile| (tile.coord, tile.kind != TileKind::Water))
+        .collect();
+    let is_passable = |c: &hex::HexCoord| passable.get(c).copied().unwrap_or(false);
+    let Some(target) = pathfinding::closest_passable(&target, is_passable, 2) else {
+        return;
+    };
+    let cost = |c: &hex::HexCoord| is_passable(c).then_some(1);
+    let Some(path) = pathfinding::astar(token.coord, target, cost) else {
+        return;
+    };
+    commands
+        .entity(entity)
+        .insert(MoveAlongPath::new(path.into_iter().skip(1).collect()));
+    selected.0 = Some(target);
+}
+
+/// Step tokens along their paths, one tile at a time
+pub fn follow_path(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tokens: Query<(Entity, &mut Token, &mut MoveAlongPath)>,
+) {
+    for (entity, mut token, mut movement) in &mut tokens {
+        movement.step.tick(time.delta());
+        for _ in 0..movement.step.times_finished_this_tick() {
+            if movement.path.is_empty() {
+                break;
+            }
+            token.coord = movement.path.remove(0);
+        }
+        if movement.path.is_empty() {
+            commands.entity(entity).remove::<MoveAlongPath>();
+        }
+    }
+}
+
+/// Stand each token on top of its tile
+pub fn place_tokens(
+    index: Res<TileIndex>,
+    tiles: Query<&Transform, (With<Tile>, Without<Token>)>,
+    mut tokens: Query<(&Token, &mut Transform)>,
+) {
+    for (token, mut transform) in &mut tokens {
+        let tile = index.0.get(&token.coord).and_then(|e| tiles.get(*e).ok());
+        if let Some(tile) = tile {
+            transform.translation = tile.translation + Vec3::Y * TOKEN_HEIGHT;
+        }
+    }
+}
+
+/// Every tile entity, by coordinate
+#[derive(Resource, Default)]
+pub struct TileIndex(pub HexMap<Entity>);
+
+/// How far the hovered tile's color is lightened toward white
+const HOVER_BRIGHTEN: f32 = 0.3;
+
+/// Light up the hovered tile by swapping in a brighter copy of its material, and put its own material back once the
+/// cursor moves on. A tile that's flashing is left to [tile_flash], and lights up once the flash is over.
+/// If something else gives the lit tile a new material while it's hovered, like an edit recoloring it, that one is lit
+/// up in turn and put back afterwards instead
+pub fn highlight_hovered_tile(
+    hovered: Res<HoveredHex>,
+    index: Res<TileIndex>,
+    mut lit: Local<Option<(Entity, Handle<StandardMaterial>)>>,
+    mut materials: TileMaterials,
+    mut tiles: Query<(&mut Handle<StandardMaterial>, Has<TileFlash>), With<Tile>>,
+) {
+    let target = hovered
+        .0
+        .and_then(|c| index.0.get(&c).copied())
+        .filter(|e| tiles.get(*e).is_ok_and(|(_, flashing)| !flashing));
+    if let Some((entity, saved)) = lit.take() {
+        if let Ok((mut handle, _)) = tiles.get_mut(entity) {
+            // A material swapped in over the highlight is the tile's own now, and there's nothing to put back
+            if materials.is_highlight(&handle) {
+                if Some(entity) == target {
+                    *lit = Some((entity, saved));
+                    return;
+                }
+                *handle = saved;
+            }
+        }
+    }
+    let Some(entity) = target else {
+        return;
+    };
+    let Ok((mut handle, _)) = tiles.get_mut(entity) else {
+        return;
+    };
+    if let Some(highlight) = materials.highlight(&handle) {
+        *lit = Some((entity, std::mem::replace(&mut *handle, highlight)));
+    }
+}
+
+/// Keep the [TileIndex] up to date as tiles are spawned
+pub fn index_tiles(mut index: ResMut<TileIndex>, tiles: Query<(Entity, &Tile), Added<Tile>>) {
+    for (entity, tile) in &tiles {
+        index.0.insert(tile.coord, entity);
+    }
+}
+
+/// Wipe the tiles at these coordinates off the map, along with anything attached to them
+#[derive(Event)]
+pub struct ClearRegion(pub HashSet<hex::HexCoord>);
+
+/// Despawn the tiles in each [ClearRegion], taking them out of the [TileIndex] too
+pub fn clear_regions(
+    mut commands: Commands,
+    mut events: EventReader<ClearRegion>,
+    mut index: ResMut<TileIndex>,
+) {
+    for ClearRegion(region) in events.read() {
+        for c in region {
+            if let Some(entity) = index.0.remove(c) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// The faction that controls a tile, by index into [FACTION_COLORS]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Owner(pub usize);
+
+/// The color each faction's borders are drawn in
+const FACTION_COLORS: [Color; 3] = [Color::CRIMSON, Color::TEAL, Color::GOLD];
+
+/// Carve the land up between the factions with T, each taking the tiles nearest a randomly placed capital
+pub fn claim_territory(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    tiles: Query<(Entity, &Tile)>,
+) {
+    if !input.just_pressed(KeyCode::T) {
+        return;
+    }
+    let mut map = HexMap::new();
+    for (_, tile) in &tiles {
+        map.insert(
+            tile.coord,
+            TileData {
+                kind: tile.kind,
+                height: tile.height,
+            },
+        );
+    }
+    let capitals = terrain::scatter_pois(&map, FACTION_COLORS.len(), 6, &mut thread_rng());
+    let coords: Vec<_> = tiles.iter().map(|(_, tile)| tile.coord).collect();
+    let regions = terrain::assign_regions(&capitals, &coords);
+    for (entity, tile) in &tiles {
+        if let Some(region) = regions.get(&tile.coord) {
+            commands.entity(entity).insert(Owner(*region));
+        }
+    }
+}
+
+/// Every edge where a tile meets a neighbor with a different owner, as the tile paired with that neighbor.
+/// Each edge shows up once from either side, so both factions can draw their half of it
+fn ownership_borders(
+    owners: &HashMap<hex::HexCoord, usize>,
+) -> Vec<(hex::HexCoord, hex::HexCoord)> {
+    owners
+        .iter()
+        .flat_map(|(c, owner)| {
+            c.neighbors()
+                .filter(|n| owners.get(n).is_some_and(|other| other != owner))
+                .map(|n| (*c, n))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Outline each faction's territory in its color, along the borders with other factions
+pub fn draw_ownership_borders(mut gizmos: Gizmos, tiles: Query<(&Tile, &Owner)>) {
+    let owners: HashMap<_, _> = tiles
+        .iter()
+        .map(|(tile, owner)| (tile.coord, owner.0))
+        .collect();
+    let heights: HashMap<_, _> = tiles
+        .iter()
+        .map(|(tile, _)| (tile.coord, tile.height))
+        .collect();
+    for (c, neighbor) in ownership_borders(&owners) {
+        let color = FACTION_COLORS[owners[&c] % FACTION_COLORS.len()];
+        let center = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &c,
+            &[0., 0., 0.],
+        ));
+        let height = heights[&c] + 0.05;
+        // Pull each side's line a little toward its own tile, so the two colors sit side by side
+        let [start, end] =
+            geometry::edge_between(MAP_LAYOUT, geometry::DEFAULT_HEX_RADIUS, &c, &neighbor).map(
+                |p| {
+                    let p = Vec3::from(p);
+                    let p = p + (center - p) * 0.1;
+                    Vec3::new(p.x, height, p.z)
+                },
+            );
+        gizmos.line(start, end, color);
+    }
+}
+
+/// The shapes of ground an action can hit, each with how far it reaches
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AreaShape {
+    /// Every tile within reach, including the middle
+    Disk(usize),
+    /// Just the tiles exactly that far away
+    Ring(usize),
+    /// A cone spreading out from the middle toward where it's aimed
+    Wedge(usize),
+    /// A straight line from the middle toward where it's aimed, leaving out the middle itself
+    Line(usize),
+}
+
+impl AreaShape {
+    /// The tiles hit by this shape, centered on `origin` and aimed at `aim`.
+    /// Wedges and lines need something to aim at that isn't the middle, or they hit nothing
+    pub fn tiles(&self, origin: &hex::HexCoord, aim: Option<hex::HexCoord>) -> Vec<hex::HexCoord> {
+        let toward = aim.filter(|aim| aim != origin);
+        match *self {
+            AreaShape::Disk(radius) => origin.range(radius),
+            AreaShape::Ring(radius) => origin.ring(radius),
+            AreaShape::Wedge(radius) => {
+                let Some(aim) = toward else {
+                    return vec![];
+                };
+                let facing = hex::Direction::between(origin, &origin.line_to(&aim)[1]);
+                let ahead = origin.neighbor(facing);
+                // Everything that stepping forward brings closer
+                origin
+                    .range(radius)
+                    .into_iter()
+                    .filter(|c| ahead.distance(c) + 1 == origin.distance(c))
+                    .collect()
+            }
+            AreaShape::Line(length) => toward
+                .map(|aim| {
+                    origin
+                        .line_to(&aim)
+                        .into_iter()
+                        .skip(1)
+                        .take(length)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// An action waiting to be confirmed, centered on `origin`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AreaAction {
+    pub origin: hex::HexCoord,
+    pub shape: AreaShape,
+}
+
+/// The action being aimed, if any
+#[derive(Resource, Default)]
+pub struct PendingAction(pub Option<AreaAction>);
+
+/// Marks the tiles the pending action would hit
+#[derive(Component)]
+pub struct AreaPreview;
+
+/// The color the previewed tiles are outlined, and flashed when the action goes off
+const AREA_COLOR: Color = Color::ORANGE_RED;
+
+/// Start aiming an action from the selected tile with 5 (disk), 6 (ring), 7 (wedge) or 8 (line).
+/// Enter sets it off, flashing the tiles it hits, and Escape calls it off
+pub fn aim_area_action(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    selected: Res<SelectedHex>,
+    hovered: Res<HoveredHex>,
+    mut pending: ResMut<PendingAction>,
+    tiles: Query<(Entity, &Tile)>,
+) {
+    let shapes = [
+        (KeyCode::Key5, AreaShape::Disk(2)),
+        (KeyCode::Key6, AreaShape::Ring(2)),
+        (KeyCode::Key7, AreaShape::Wedge(3)),
+        (KeyCode::Key8, AreaShape::Line(4)),
+    ];
+    if let Some(origin) = selected.0 {
+        for (key, shape) in shapes {
+            if input.just_pressed(key) {
+                pending.0 = Some(AreaAction { origin, shape });
+            }
+        }
+    }
+    if input.just_pressed(KeyCode::Escape) {
+        pending.0 = None;
+    } else if input.just_pressed(KeyCode::Return) {
+        let Some(action) = pending.0.take() else {
+            return;
+        };
+        let hit: HashSet<_> = action
+            .shape
+            .tiles(&action.origin, hovered.0)
+            .into_iter()
+            .collect();
+        for (entity, tile) in &tiles {
+            if hit.contains(&tile.coord) {
+                commands
+                    .entity(entity)
+                    .insert(TileFlash::new(AREA_COLOR, 0.5));
+            }
+        }
+    }
+}
+
+/// Mark exactly the tiles the pending action would hit, following the cursor as it's aimed
+pub fn preview_area(
+    mut commands: Commands,
+    pending: Res<PendingAction>,
+    hovered: Res<HoveredHex>,
+    tiles: Query<(Entity, &Tile, Has<AreaPreview>)>,
+) {
+    let hit: HashSet<_> = pending
+        .0
+        .map(|action| action.shape.tiles(&action.origin, hovered.0))
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    for (entity, tile, previewed) in &tiles {
+        match (hit.contains(&tile.coord), previewed) {
+            (true, false) => {
+                commands.entity(entity).insert(AreaPreview);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<AreaPreview>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Draw a hexagon just inside the edge of `tile`, and just above its surface
+fn outline_tile(gizmos: &mut Gizmos, tile: &Tile, color: Color) {
+    let mut pts = vec![];
+    let center = geometry::center_with_layout(
+        MAP_LAYOUT,
+        geometry::DEFAULT_HEX_RADIUS,
+        &tile.coord,
+        &[0., tile.height + 0.05, 0.],
+    );
+    geometry::flat_hexagon_ring(
+        &mut pts,
+        geometry::DEFAULT_HEX_RADIUS * 0.9,
+        &hex::HexCoord::origin(),
+        &center,
+    );
+    gizmos.linestrip(pts.into_iter().map(Vec3::from), color);
+}
+
+/// Outline each previewed tile
+pub fn draw_area_preview(mut gizmos: Gizmos, tiles: Query<&Tile, With<AreaPreview>>) {
+    for tile in &tiles {
+        outline_tile(&mut gizmos, tile, AREA_COLOR);
+    }
+}
+
+/// Everything the player was doing during one frame of a recording
+#[derive(Clone, PartialEq, Debug)]
+pub struct InputFrame {
+    /// How long the frame took, so replaying it moves things just as far
+    pub delta: Duration,
+    pub keys: Vec<KeyCode>,
+    pub buttons: Vec<MouseButton>,
+    pub hovered: Option<hex::HexCoord>,
+}
+
+/// A recorded stretch of input, along with where the camera and selection started, so it can be played back exactly
+#[derive(Clone, Default, Debug)]
+pub struct InputRecording {
+    pub camera: Transform,
+    pub camera_velocity: Vec3,
+    pub selected: Option<hex::HexCoord>,
+    pub frames: Vec<InputFrame>,
+}
+
+/// What the [InputRecorder] is up to
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum RecorderMode {
+    #[default]
+    Idle,
+    Recording,
+    /// Playing back the recording, with `next` the frame to play next
+    Replaying {
+        next: usize,
+    },
+}
+
+/// Records input with F9, and replays the last recording with F10, for reproducing interaction bugs
+#[derive(Resource, Default)]
+pub struct InputRecorder {
+    pub mode: RecorderMode,
+    pub recording: InputRecording,
+}
+
+/// The keys that drive the recorder, which are left out of recordings so playing one back doesn't start another
+const RECORDER_KEYS: [KeyCode; 2] = [KeyCode::F9, KeyCode::F10];
+
+/// Start or stop recording with F9, or start replaying with F10
+pub fn toggle_recording(
+    input: Res<Input<KeyCode>>,
+    mut recorder: ResMut<InputRecorder>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    selected: Res<SelectedHex>,
+    movement: Res<CameraMovement>,
+    camera: Query<&Transform, (With<Camera>, Without<ReflectionCamera>)>,
+) {
+    if matches!(recorder.mode, RecorderMode::Replaying { .. }) {
+        return;
+    }
+    if input.just_pressed(KeyCode::F9) {
+        if recorder.mode == RecorderMode::Recording {
+            recorder.mode = RecorderMode::Idle;
+        } else {
+            recorder.mode = RecorderMode::Recording;
+            recorder.recording = InputRecording {
+                camera: camera.get_single().copied().unwrap_or_default(),
+                camera_velocity: movement.velocity,
+                selected: selected.0,
+                frames: vec![],
+            };
+        }
+    } else if input.just_pressed(KeyCode::F10) {
+        if let Some(first) = recorder.recording.frames.first() {
+            // Time for this frame has already been measured, so playback starts from the next one
+            *time_strategy = TimeUpdateStrategy::ManualDuration(first.delta);
+            recorder.mode = RecorderMode::Replaying { next: 0 };
+        }
+    }
+}
+
+/// Capture this frame's input, while recording
+pub fn record_input(
+    mut recorder: ResMut<InputRecorder>,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    hovered: Res<HoveredHex>,
+) {
+    if recorder.mode != RecorderMode::Recording {
+        return;
+    }
+    let frame = InputFrame {
+        delta: time.delta(),
+        keys: keys
+            .get_pressed()
+            .filter(|key| !RECORDER_KEYS.contains(key))
+            .copied()
+            .collect(),
+        buttons: buttons.get_pressed().copied().collect(),
+        hovered: hovered.0,
+    };
+    recorder.recording.frames.push(frame);
+}
+
+/// Overwrite `input` so exactly `now` are held, as if they'd been pressed and released since `before`
+fn replay_buttons<T: Copy + Eq + std::hash::Hash + Send + Sync + 'static>(
+    input: &mut Input<T>,
+    before: &[T],
+    now: &[T],
+) {
+    input.reset_all();
+    for button in now {
+        input.press(*button);
+        if before.contains(button) {
+            input.clear_just_pressed(*button);
+        }
+    }
+    for button in before.iter().filter(|button| !now.contains(button)) {
+        input.press(*button);
+        input.release(*button);
+        input.clear_just_pressed(*button);
+    }
+}
+
+/// The input that a replay overwrites each frame
+#[derive(SystemParam)]
+pub struct ReplayedInput<'w> {
+    keys: ResMut<'w, Input<KeyCode>>,
+    buttons: ResMut<'w, Input<MouseButton>>,
+    hovered: ResMut<'w, HoveredHex>,
+}
+
+/// Play back the recording a frame at a time, standing in for the real input
+pub fn replay_input(
+    mut recorder: ResMut<InputRecorder>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut input: ReplayedInput,
+    mut selected: ResMut<SelectedHex>,
+    mut movement: ResMut<CameraMovement>,
+    mut camera: Query<&mut Transform, (With<Camera>, Without<ReflectionCamera>)>,
+) {
+    let RecorderMode::Replaying { next } = recorder.mode else {
+        return;
+    };
+    let recording = &recorder.recording;
+    if next == 0 {
+        if let Ok(mut transform) = camera.get_single_mut() {
+            *transform = recording.camera;
+        }
+        movement.velocity = recording.camera_velocity;
+        selected.0 = recording.selected;
+    }
+    let frame = &recording.frames[next];
+    let before = next.checked_sub(1).map(|i| &recording.frames[i]);
+    replay_buttons(
+        &mut input.keys,
+        before.map_or(&[][..], |b| &b.keys),
+        &frame.keys,
+    );
+    replay_buttons(
+        &mut input.buttons,
+        before.map_or(&[][..], |b| &b.buttons),
+        &frame.buttons,
+    );
+    input.hovered.0 = frame.hovered;
+
+    // Line up the timing of the frame after this one too, since it's measured before we get to run again
+    match recording.frames.get(next + 1) {
+        Some(following) => {
+            *time_strategy = TimeUpdateStrategy::ManualDuration(following.delta);
+            recorder.mode = RecorderMode::Replaying { next: next + 1 };
+        }
+        None => {
+            *time_strategy = TimeUpdateStrategy::Automatic;
+            recorder.mode = RecorderMode::Idle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn water_scroll_advances_with_time() {
+        let mut scroll = WaterScroll {
+            speed: Vec2::new(0.1, 0.2),
+            offset: Vec2::ZERO,
+        };
+        scroll.advance(1.);
+        assert!((scroll.offset - Vec2::new(0.1, 0.2)).length() < 1e-6);
+        scroll.advance(1.);
+        assert!((scroll.offset - Vec2::new(0.2, 0.4)).length() < 1e-6);
+        // Wraps around instead of growing forever
+        scroll.advance(4.);
+        assert!(scroll.offset.x < 1. && scroll.offset.y < 1.);
+    }
+
+    #[test]
+    fn scrolling_water_shifts_its_uvs() {
+        let mut app = App::new();
+        app.add_plugins(bevy::time::TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)))
+            .init_resource::<Assets<Mesh>>()
+            .insert_resource(WaterScroll {
+                speed: Vec2::new(0.1, 0.2),
+                offset: Vec2::ZERO,
+            })
+            .add_systems(Update, scroll_water);
+        let mesh = generate_hex_mesh(&BevelConfig::default());
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            panic!("hex mesh should have UVs");
+        };
+        let uvs = uvs.clone();
+        let mesh = app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+        app.insert_resource(WaterMesh {
+            mesh: mesh.clone(),
+            uvs: uvs.clone(),
+        });
+        app.update();
+        app.update();
+
+        let offset = app.world.resource::<WaterScroll>().offset;
+        assert!(offset.length() > 0.);
+        let meshes = app.world.resource::<Assets<Mesh>>();
+        let Some(VertexAttributeValues::Float32x2(scrolled)) =
+            meshes.get(&mesh).unwrap().attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            panic!("water mesh should still have UVs");
+        };
+        assert_eq!(uvs.len(), scrolled.len());
+        for ([u, v], [su, sv]) in uvs.iter().zip(scrolled) {
+            assert_eq!([u + offset.x, v + offset.y], [*su, *sv]);
+        }
+    }
+
+    #[test]
+    fn water_is_drawn_with_repeating_caustics() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<MaterialCache>()
+            .insert_resource(MapConfig {
+                size: 8,
+                background: false,
+                seed: 3,
+                ..Default::default()
+            })
+            .init_resource::<RenderQuality>()
+            .init_resource::<BevelConfig>()
+            .add_systems(Startup, sample_level);
+        app.update();
+
+        let mut water = app
+            .world
+            .query_filtered::<&Handle<StandardMaterial>, With<Water>>();
+        let handles: HashSet<_> = water.iter(&app.world).cloned().collect();
+        assert_eq!(1, handles.len());
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        let material = materials.get(handles.iter().next().unwrap()).unwrap();
+        let images = app.world.resource::<Assets<Image>>();
+        let caustics = images
+            .get(material.base_color_texture.as_ref().unwrap())
+            .unwrap();
+        let ImageSampler::Descriptor(sampler) = &caustics.sampler else {
+            panic!("caustics should repeat as they scroll");
+        };
+        assert!(matches!(sampler.address_mode_u, ImageAddressMode::Repeat));
+        assert!(matches!(sampler.address_mode_v, ImageAddressMode::Repeat));
+    }
+
+    #[test]
+    fn tile_flash_restores_original_color() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .add_systems(Update, tile_flash);
+        let original = Color::rgb(0.1, 0.2, 0.3);
+        let material = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(original.into());
+        let tile = app
+            .world
+            .spawn((material.clone(), TileFlash::new(Color::WHITE, 1.)))
+            .id();
+
+        let color = |app: &App| {
+            app.world
+                .resource::<Assets<StandardMaterial>>()
+                .get(&material)
+                .unwrap()
+                .base_color
+        };
+
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(400));
+        app.update();
+        assert_ne!(original, color(&app));
+
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_millis(800));
+        app.update();
+        assert_eq!(original, color(&app));
+        assert!(app.world.get::<TileFlash>(tile).is_none());
+    }
+
+    #[test]
+    fn axis_arrows_point_along_each_axis() {
+        let arrows = axis_arrows(2.);
+        assert_eq!(3, arrows.len());
+        // +q runs straight along x
+        assert!((arrows[0].0 - Vec3::new(2., 0., 0.)).length() < 1e-5);
+        for (i, (a, _)) in arrows.iter().enumerate() {
+            assert!((a.length() - 2.).abs() < 1e-5);
+            let (b, _) = arrows[(i + 1) % 3];
+            // Each pair is 120 degrees apart
+            assert!((a.normalize().dot(b.normalize()) + 0.5).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn axis_labels_follow_the_arrows() {
+        use bevy::render::camera::{camera_system, ManualTextureViews};
+        use bevy::window::{WindowCreated, WindowResized};
+
+        let mut app = App::new();
+        app.add_event::<WindowCreated>()
+            .add_event::<WindowResized>()
+            .add_event::<AssetEvent<Image>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<ManualTextureViews>()
+            .init_resource::<ShowAxes>()
+            .add_systems(Startup, spawn_axis_labels)
+            .add_systems(
+                Update,
+                (camera_system::<Projection>, place_axis_labels).chain(),
+            );
+        app.world.spawn((Window::default(), PrimaryWindow));
+        // Looking along +q, like the sample's camera does
+        let transform = Transform::from_xyz(-10., 15., 0.).looking_at(Vec3::ZERO, Vec3::Y);
+        app.world.spawn(Camera3dBundle {
+            transform,
+            global_transform: transform.into(),
+            ..Default::default()
+        });
+        let labels = |app: &mut App| {
+            let mut query = app
+                .world
+                .query::<(&AxisLabel, &Text, &Style, &Visibility)>();
+            let mut labels: Vec<_> = query
+                .iter(&app.world)
+                .map(|(label, text, style, visibility)| {
+                    // Where the label sits down the screen, if it's showing
+                    let top = match (visibility, style.top) {
+                        (Visibility::Inherited, Val::Px(top)) => Some(top),
+                        _ => None,
+                    };
+                    (label.0, text.sections[0].value.clone(), top)
+                })
+                .collect();
+            labels.sort_by_key(|(i, ..)| *i);
+            labels
+        };
+
+        app.update();
+        assert!(labels(&mut app).iter().all(|(.., top)| top.is_none()));
+
+        app.world.resource_mut::<ShowAxes>().0 = true;
+        app.update();
+        let shown = labels(&mut app);
+        let names: Vec<_> = shown.iter().map(|(_, name, _)| name.as_str()).collect();
+        assert_eq!(vec!["+q", "+r", "+s"], names);
+        let tops: Vec<_> = shown.iter().map(|(.., top)| top.unwrap()).collect();
+        // +q points away from the camera, so its label is the highest up the screen
+        assert!(tops[0] < tops[1] && tops[0] < tops[2]);
+
+        app.world.resource_mut::<ShowAxes>().0 = false;
+        app.update();
+        assert!(labels(&mut app).iter().all(|(.., top)| top.is_none()));
+    }
+
+    #[test]
+    fn tide_floods_and_drains_shoreline() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<WaterLevel>()
+            .insert_resource(Tide {
+                period: 4.,
+                amplitude: 0.4,
+            })
+            .add_systems(Update, (tide, flood_tiles).chain());
+        let material = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::GREEN.into());
+        let shore = app
+            .world
+            .spawn((
+                Tile {
+                    coord: hex::HexCoord::origin(),
+                    kind: TileKind::Grass,
+                    height: 0.2,
+                    color: Color::GREEN,
+                },
+                material,
+                Transform::default(),
+            ))
+            .id();
+
+        let mut underwater_at = |seconds: f32| {
+            app.world
+                .resource_mut::<Time>()
+                .advance_to(Duration::from_secs_f32(seconds));
+            app.update();
+            app.world.get::<Water>(shore).is_some()
+        };
+        assert!(!underwater_at(0.));
+        // High tide
+        assert!(underwater_at(1.));
+        // Low tide
+        assert!(!underwater_at(3.));
+        // And back up again on the next cycle
+        assert!(underwater_at(5.));
+    }
+
+    #[test]
+    fn framing_keeps_every_tile_in_view() {
+        let coords: Vec<_> = (-15..15)
+            .flat_map(|q| (-15..15).map(move |r| hex::HexCoord::new(q, r)))
+            .collect();
+        let (min, max) =
+            geometry::region_aabb(MAP_LAYOUT, geometry::DEFAULT_HEX_RADIUS, &coords).unwrap();
+        let (fov, aspect) = (std::f32::consts::FRAC_PI_4, 16. / 9.);
+        let forward = Vec3::new(1., -1.5, 0.).normalize();
+        let position = framing_position(min.into(), max.into(), forward, fov, aspect);
+
+        let view = Transform::from_translation(position)
+            .looking_to(forward, Vec3::Y)
+            .compute_matrix()
+            .inverse();
+        let (tan_v, tan_h) = ((fov / 2.).tan(), (fov / 2.).tan() * aspect);
+        for c in &coords {
+            let p = view.transform_point3(
+                geometry::center_with_layout(
+                    MAP_LAYOUT,
+                    geometry::DEFAULT_HEX_RADIUS,
+                    c,
+                    &[0., 0., 0.],
+                )
+                .into(),
+            );
+            // Cameras look down -z
+            let depth = -p.z;
+            assert!(depth > 0.);
+            assert!(p.x.abs() / depth <= tan_h);
+            assert!(p.y.abs() / depth <= tan_v);
+        }
+    }
+
+    #[test]
+    fn hovering_highlights_every_tile_of_that_kind() {
+        let mut app = App::new();
+        app.init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<HoveredHex>()
+            .add_systems(Update, highlight_hovered_kind);
+        let kinds = [
+            TileKind::Grass,
+            TileKind::Water,
+            TileKind::Grass,
+            TileKind::Hills,
+        ];
+        let tiles: Vec<_> = kinds
+            .iter()
+            .enumerate()
+            .map(|(q, kind)| {
+                let material = app
+                    .world
+                    .resource_mut::<Assets<StandardMaterial>>()
+                    .add(Color::WHITE.into());
+                let tile = Tile {
+                    coord: hex::HexCoord::new(q as isize, 0),
+                    kind: *kind,
+                    height: 0.,
+                    color: Color::WHITE,
+                };
+                app.world.spawn((tile, material.clone()));
+                (*kind, material)
+            })
+            .collect();
+
+        app.world.resource_mut::<HoveredHex>().0 = Some(hex::HexCoord::new(2, 0));
+        app.update();
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        for (kind, material) in &tiles {
+            let highlighted = materials.get(material).unwrap().emissive == KIND_HIGHLIGHT;
+            assert_eq!(*kind == TileKind::Grass, highlighted);
+        }
+
+        app.world.resource_mut::<HoveredHex>().0 = None;
+        app.update();
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        for (_, material) in &tiles {
+            assert_eq!(Color::BLACK, materials.get(material).unwrap().emissive);
+        }
+    }
+
+    #[test]
+    fn background_generation_spawns_every_tile() {
+        AsyncComputeTaskPool::get_or_init(Default::default);
+        let config = MapConfig {
+            size: 4,
+            ..Default::default()
+        };
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<Assets<Image>>()
+            .insert_resource(config.clone())
+            .init_resource::<RenderQuality>()
+            .init_resource::<BevelConfig>()
+            .add_systems(Startup, sample_level)
+            .add_systems(Update, finish_map_generation);
+
+        app.update();
+        while app.world.contains_resource::<MapGeneration>() {
+            std::thread::yield_now();
+            app.update();
+        }
+        let tiles = app.world.query::<&Tile>().iter(&app.world).count();
+        assert_eq!((2 * config.size * 2 * config.size) as usize, tiles);
+    }
+
+    #[test]
+    fn cursor_ray_lands_on_the_right_tile() {
+        let target = hex::HexCoord::new(3, -2);
+        let center = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &target,
+            &[0., 0., 0.],
+        ));
+        let origin = Vec3::new(-10., 15., 0.);
+        let hit = ground_intersection(origin, center - origin).unwrap();
+        assert!((hit - center).length() < 1e-4);
+        assert_eq!(
+            target,
+            geometry::hex_at_point(MAP_LAYOUT, geometry::DEFAULT_HEX_RADIUS, &hit.to_array())
+        );
+
+        // Looking at the horizon, or up at the sky, never hits the ground
+        assert_eq!(None, ground_intersection(origin, Vec3::X));
+        assert_eq!(None, ground_intersection(origin, Vec3::Y));
+    }
+
+    #[test]
+    fn undo_restores_edited_tile() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<EditHistory>()
+            .init_resource::<MapConfig>()
+            .insert_resource(HoveredHex(Some(hex::HexCoord::origin())))
+            .add_systems(Update, (edit_terrain, undo_redo).chain());
+        let material = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(kind_color(TileKind::Grass).into());
+        let tile = app
+            .world
+            .spawn((
+                Tile {
+                    coord: hex::HexCoord::origin(),
+                    kind: TileKind::Grass,
+                    height: 0.5,
+                    color: kind_color(TileKind::Grass),
+                },
+                Transform::from_xyz(0., 0.5, 0.),
+                material,
+            ))
+            .id();
+
+        let press = |app: &mut App, keys: &[KeyCode]| {
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            input.release_all();
+            input.clear();
+            for key in keys {
+                input.press(*key);
+            }
+            app.update();
+        };
+        let state = |app: &App| {
+            let tile = app.world.get::<Tile>(tile).unwrap();
+            (tile.kind, tile.height)
+        };
+
+        press(&mut app, &[KeyCode::PageUp]);
+        press(&mut app, &[KeyCode::Key4]);
+        assert_eq!((TileKind::Hills, 0.75), state(&app));
+
+        press(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert_eq!((TileKind::Grass, 0.75), state(&app));
+        press(&mut app, &[KeyCode::ControlLeft, KeyCode::Z]);
+        assert_eq!((TileKind::Grass, 0.5), state(&app));
+        assert_eq!(0.5, app.world.get::<HeightTransition>(tile).unwrap().to);
+
+        press(&mut app, &[KeyCode::ControlLeft, KeyCode::Y]);
+        assert_eq!((TileKind::Grass, 0.75), state(&app));
+    }
+
+    #[test]
+    fn edited_height_eases_into_place() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<EditHistory>()
+            .init_resource::<MapConfig>()
+            .insert_resource(HoveredHex(Some(hex::HexCoord::origin())))
+            .add_systems(Update, (edit_terrain, animate_heights).chain());
+        let tile = app
+            .world
+            .spawn((
+                Tile {
+                    coord: hex::HexCoord::origin(),
+                    kind: TileKind::Grass,
+                    height: 0.5,
+                    color: kind_color(TileKind::Grass),
+                },
+                Transform::from_xyz(0., 0.5, 0.),
+                Handle::<StandardMaterial>::default(),
+            ))
+            .id();
+        let height = |app: &App| app.world.get::<Transform>(tile).unwrap().translation.y;
+        let step = |app: &mut App, seconds: f32| {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs_f32(seconds));
+            app.update();
+            app.world.resource_mut::<Input<KeyCode>>().clear();
+        };
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::PageUp);
+        step(&mut app, 0.);
+        assert_eq!(0.5, height(&app));
+        step(&mut app, 0.1);
+        let partway = height(&app);
+        assert!(0.5 < partway && partway < 0.75);
+        step(&mut app, 0.1);
+        assert!(partway < height(&app));
+        step(&mut app, 1.);
+        assert_eq!(0.75, height(&app));
+        assert!(app.world.get::<HeightTransition>(tile).is_none());
+    }
+
+    #[test]
+    fn backdrop_is_applied_to_camera() {
+        let clear_color = Color::rgb(0.1, 0.2, 0.3);
+        let mut app = App::new();
+        app.init_resource::<Assets<Image>>()
+            .insert_resource(Backdrop {
+                clear_color,
+                sky: None,
+            })
+            .add_systems(Startup, spawn_camera);
+        app.update();
+
+        let (camera, skybox) = app
+            .world
+            .query::<(&Camera3d, Option<&Skybox>)>()
+            .single(&app.world);
+        assert!(matches!(camera.clear_color, ClearColorConfig::Custom(c) if c == clear_color));
+        assert!(skybox.is_none());
+    }
+
+    #[test]
+    fn selection_ring_glows() {
+        let style = BorderStyle::default();
+        let material = glow_material(style.color);
+        assert_ne!(Color::BLACK, material.emissive);
+        assert!(material.unlit);
+
+        let mesh = generate_selection_ring_mesh(&style);
+        let Some(VertexAttributeValues::Float32x3(points)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("selection ring should have positions");
+        };
+        let outer = points
+            .iter()
+            .map(|[x, _, z]| (x * x + z * z).sqrt())
+            .fold(0., f32::max);
+        assert!((outer - geometry::DEFAULT_HEX_RADIUS).abs() < 1e-5);
+    }
+
+    #[test]
+    fn resource_icons_follow_tile_kind() {
+        let mut app = App::new();
+        app.insert_resource(ResourceIcons {
+            wood: Default::default(),
+            ore: Default::default(),
+        })
+        .add_systems(Update, spawn_resource_icons);
+        let spawn = |app: &mut App, kind| {
+            let tile = Tile {
+                coord: hex::HexCoord::origin(),
+                kind,
+                height: 0.,
+                color: Color::WHITE,
+            };
+            app.world.spawn(tile).id()
+        };
+        let forest = spawn(&mut app, TileKind::Forest);
+        let water = spawn(&mut app, TileKind::Water);
+        app.update();
+
+        let icons = |app: &App, tile| -> Vec<TileResource> {
+            let Some(children) = app.world.get::<Children>(tile) else {
+                return vec![];
+            };
+            children
+                .iter()
+                .filter_map(|child| app.world.get::<ResourceIcon>(*child))
+                .map(|icon| icon.0)
+                .collect()
+        };
+        assert_eq!(vec![TileResource::Wood], icons(&app, forest));
+        assert!(icons(&app, water).is_empty());
+
+        // Clearing the forest takes the wood with it
+        app.world.get_mut::<Tile>(forest).unwrap().kind = TileKind::Grass;
+        app.update();
+        assert!(icons(&app, forest).is_empty());
+    }
+
+    #[test]
+    fn border_style_rebuilds_the_outline() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<BorderStyle>()
+            .add_systems(Startup, spawn_selection_ring)
+            .add_systems(
+                Update,
+                restyle_selection_ring.run_if(resource_changed::<BorderStyle>()),
+            );
+        app.update();
+
+        let width = |app: &mut App| {
+            let handle = app
+                .world
+                .query_filtered::<&Handle<Mesh>, With<SelectionRing>>()
+                .single(&app.world)
+                .clone();
+            let meshes = app.world.resource::<Assets<Mesh>>();
+            let Some(VertexAttributeValues::Float32x3(points)) = meshes
+                .get(&handle)
+                .unwrap()
+                .attribute(Mesh::ATTRIBUTE_POSITION)
+            else {
+                panic!("selection ring should have positions");
+            };
+            let radii: Vec<f32> = points
+                .iter()
+                .map(|[x, _, z]| (x * x + z * z).sqrt())
+                .collect();
+            radii.iter().copied().fold(0., f32::max) - radii.iter().copied().fold(1., f32::min)
+        };
+        assert!((width(&mut app) - 0.12).abs() < 1e-5);
+
+        let gold = app.world.resource::<BorderStyle>().color;
+        let mut style = app.world.resource_mut::<BorderStyle>();
+        style.thickness = 0.3;
+        style.color = Color::RED;
+        app.update();
+        assert!((width(&mut app) - 0.3).abs() < 1e-5);
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        let (_, material) = materials.iter().next().unwrap();
+        assert_eq!(Color::RED, material.base_color);
+        assert_ne!(gold, material.base_color);
+    }
+
+    #[test]
+    fn clicking_a_tile_paths_the_token_there() {
+        let mut app = App::new();
+        app.init_resource::<Input<MouseButton>>()
+            .init_resource::<HoveredHex>()
+            .init_resource::<SelectedHex>()
+            .add_systems(Update, click_to_move);
+        let origin = hex::HexCoord::origin();
+        // A wall of water straight between the token and its target
+        let wall = [origin.northeast(), origin.northeast().northeast()];
+        for coord in origin.range(3) {
+            let kind = if wall.contains(&coord) {
+                TileKind::Water
+            } else {
+                TileKind::Grass
+            };
+            app.world.spawn(Tile {
+                coord,
+                kind,
+                height: 0.,
+                color: Color::WHITE,
+            });
+        }
+        let token = app.world.spawn(Token { coord: origin }).id();
+        let target = hex::HexCoord::new(3, -3);
+        app.world.resource_mut::<SelectedHex>().0 = Some(origin);
+        app.world.resource_mut::<HoveredHex>().0 = Some(target);
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .press(MouseButton::Right);
+        app.update();
+
+        let movement = app.world.get::<MoveAlongPath>(token).unwrap();
+        assert_eq!(Some(&target), movement.path.last());
+        assert!(movement.path.iter().all(|c| !wall.contains(c)));
+        assert_eq!(Some(target), app.world.resource::<SelectedHex>().0);
+    }
+
+    #[test]
+    fn tokens_step_between_touching_tiles() {
+        let mut app = App::new();
+        app.init_resource::<TileIndex>()
+            .add_systems(Update, (index_tiles, place_tokens).chain());
+        let origin = hex::HexCoord::origin();
+        for coord in origin.range(1) {
+            let pos = geometry::center_with_layout(
+                MAP_LAYOUT,
+                geometry::DEFAULT_HEX_RADIUS,
+                &coord,
+                &[0., 0., 0.],
+            );
+            app.world.spawn((
+                Tile {
+                    coord,
+                    kind: TileKind::Grass,
+                    height: 0.,
+                    color: Color::WHITE,
+                },
+                Transform::from_translation(pos.into()),
+            ));
+        }
+        let token = app
+            .world
+            .spawn((Token { coord: origin }, Transform::default()))
+            .id();
+        app.update();
+        let start = app.world.get::<Transform>(token).unwrap().translation;
+        assert_eq!(Vec3::Y * TOKEN_HEIGHT, start);
+
+        // Every step a path can take lands the token on the tile right next door
+        let inner = geometry::DEFAULT_HEX_RADIUS * geometry::HEX_INNER_RADIUS_RATIO;
+        for neighbor in origin.neighbors() {
+            app.world.get_mut::<Token>(token).unwrap().coord = neighbor;
+            app.update();
+            let moved = app.world.get::<Transform>(token).unwrap().translation;
+            assert!(
+                (moved.distance(start) - 2. * inner).abs() < 1e-5,
+                "{neighbor}"
+            );
+        }
+    }
+
+    #[test]
+    fn clicking_the_water_heads_for_the_shore() {
+        let mut app = App::new();
+        app.init_resource::<Input<MouseButton>>()
+            .init_resource::<HoveredHex>()
+            .init_resource::<SelectedHex>()
+            .add_systems(Update, click_to_move);
+        let origin = hex::HexCoord::origin();
+        // A lake with its middle two steps from shore, and dry land all around
+        let lake_middle = hex::HexCoord::new(3, -1);
+        let lake = lake_middle.range(1);
+        for coord in origin.range(5) {
+            let kind = if lake.contains(&coord) {
+                TileKind::Water
+            } else {
+                TileKind::Grass
+            };
+            app.world.spawn(Tile {
+                coord,
+                kind,
+                height: 0.,
+                color: Color::WHITE,
+            });
+        }
+        let token = app.world.spawn(Token { coord: origin }).id();
+        app.world.resource_mut::<SelectedHex>().0 = Some(origin);
+        app.world.resource_mut::<HoveredHex>().0 = Some(lake_middle);
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .press(MouseButton::Right);
+        app.update();
+
+        let movement = app.world.get::<MoveAlongPath>(token).unwrap();
+        let shore = *movement.path.last().unwrap();
+        assert_eq!(2, shore.distance(&lake_middle));
+        assert!(movement.path.iter().all(|c| !lake.contains(c)));
+        assert_eq!(Some(shore), app.world.resource::<SelectedHex>().0);
+    }
+
+    #[test]
+    fn idle_bob_ripples_outward() {
+        let bob = IdleBob::default();
+        let near = hex::HexCoord::origin();
+        let far = hex::HexCoord::new(3, -1);
+        assert_eq!(0., IdleBob::phase(&near));
+        assert!((IdleBob::phase(&far) - 3. * BOB_PHASE_PER_RING).abs() < 1e-6);
+        // The far tile does whatever the near one did, just a little later
+        let lag = IdleBob::phase(&far) / bob.speed;
+        for seconds in [0.3, 1.7, 4.2] {
+            let expected = bob.offset(seconds, &near);
+            assert!((bob.offset(seconds + lag, &far) - expected).abs() < 1e-5);
+        }
+        assert_ne!(bob.offset(1., &near), bob.offset(1., &far));
+    }
+
+    #[test]
+    fn cel_shading_swaps_tile_materials() {
+        let run = |cel_shading| {
+            let mut app = App::new();
+            app.init_resource::<Assets<StandardMaterial>>()
+                .init_resource::<Assets<CelMaterial>>()
+                .insert_resource(MapConfig {
+                    cel_shading,
+                    ..Default::default()
+                })
+                .add_systems(Update, apply_cel_shading);
+            let color = kind_color(TileKind::Grass);
+            let material = app
+                .world
+                .resource_mut::<Assets<StandardMaterial>>()
+                .add(color.into());
+            let tile = app
+                .world
+                .spawn((
+                    Tile {
+                        coord: hex::HexCoord::origin(),
+                        kind: TileKind::Grass,
+                        height: 0.5,
+                        color,
+                    },
+                    material,
+                ))
+                .id();
+            app.update();
+            let cel = app.world.get::<Handle<CelMaterial>>(tile).map(|handle| {
+                app.world
+                    .resource::<Assets<CelMaterial>>()
+                    .get(handle)
+                    .unwrap()
+                    .color
+            });
+            let standard = app.world.get::<Handle<StandardMaterial>>(tile).is_some();
+            (cel, standard)
+        };
+        assert_eq!((Some(kind_color(TileKind::Grass)), false), run(true));
+        assert_eq!((None, true), run(false));
+    }
+
+    #[test]
+    fn cel_shaded_tiles_still_edit_and_flood() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<EditHistory>()
+            .init_resource::<MapConfig>()
+            .init_resource::<WaterLevel>()
+            .insert_resource(HoveredHex(Some(hex::HexCoord::origin())))
+            .add_systems(Update, (edit_terrain, flood_tiles));
+        // Like a tile after `apply_cel_shading`, with no standard material
+        let tile = |coord, height| Tile {
+            coord,
+            kind: TileKind::Grass,
+            height,
+            color: kind_color(TileKind::Grass),
+        };
+        let edited = app
+            .world
+            .spawn((tile(hex::HexCoord::origin(), 0.5), Transform::default()))
+            .id();
+        let sunken = app
+            .world
+            .spawn((tile(hex::HexCoord::new(1, 0), -0.5), Transform::default()))
+            .id();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::PageUp);
+        app.update();
+
+        assert_eq!(0.75, app.world.get::<Tile>(edited).unwrap().height);
+        assert!(app.world.get::<HeightTransition>(edited).is_some());
+        assert!(app.world.get::<Water>(sunken).is_some());
+    }
+
+    #[test]
+    fn clearing_a_region_despawns_its_tiles() {
+        let mut app = App::new();
+        app.init_resource::<TileIndex>()
+            .add_event::<ClearRegion>()
+            .add_systems(Update, (index_tiles, clear_regions).chain());
+        let origin = hex::HexCoord::origin();
+        for coord in origin.range(2) {
+            app.world
+                .spawn(Tile {
+                    coord,
+                    kind: TileKind::Grass,
+                    height: 0.5,
+                    color: Color::WHITE,
+                })
+                .with_children(|parent| {
+                    parent.spawn(ResourceIcon(TileResource::Wood));
+                });
+        }
+        app.update();
+        assert_eq!(19, app.world.resource::<TileIndex>().0.len());
+
+        let region: HashSet<_> = origin.range(1).into_iter().collect();
+        app.world.send_event(ClearRegion(region.clone()));
+        app.update();
+
+        let index = &app.world.resource::<TileIndex>().0;
+        assert_eq!(12, index.len());
+        assert!(region.iter().all(|c| !index.contains(c)));
+        let mut tiles = app.world.query::<&Tile>();
+        assert_eq!(12, tiles.iter(&app.world).count());
+        assert!(tiles
+            .iter(&app.world)
+            .all(|tile| !region.contains(&tile.coord)));
+        let mut icons = app.world.query::<&ResourceIcon>();
+        assert_eq!(12, icons.iter(&app.world).count());
+    }
+
+    #[test]
+    fn ownership_borders_only_between_factions() {
+        // West of the origin belongs to faction 0, and the rest to faction 1
+        let owners: HashMap<_, _> = hex::HexCoord::origin()
+            .range(3)
+            .into_iter()
+            .map(|c| (c, usize::from(c.q >= 0)))
+            .collect();
+        let borders = ownership_borders(&owners);
+        assert!(!borders.is_empty());
+        for (a, b) in &borders {
+            assert_eq!(1, a.distance(b));
+            assert_ne!(owners[a], owners[b]);
+            assert!(borders.contains(&(*b, *a)));
+        }
+        // Every differing pair shows up
+        let expected = owners
+            .keys()
+            .flat_map(|c| c.neighbors().map(move |n| (*c, n)))
+            .filter(|(c, n)| owners.get(n).is_some_and(|o| *o != owners[c]))
+            .count();
+        assert_eq!(expected, borders.len());
+
+        let single: HashMap<_, _> = owners.keys().map(|c| (*c, 0)).collect();
+        assert!(ownership_borders(&single).is_empty());
+    }
+
+    #[test]
+    fn render_quality_presets() {
+        assert_eq!(Msaa::Off, RenderQuality::Low.msaa());
+        assert_eq!(Msaa::Sample2, RenderQuality::Medium.msaa());
+        assert_eq!(Msaa::Sample4, RenderQuality::High.msaa());
+        assert_eq!(None, RenderQuality::Low.shadow_map_size());
+        assert!(RenderQuality::Medium.shadow_map_size() < RenderQuality::High.shadow_map_size());
+        assert!(RenderQuality::Low.lod());
+        assert!(!RenderQuality::High.lod());
+        // The default matches what the example always used
+        assert_eq!(Msaa::Sample4, RenderQuality::default().msaa());
+    }
+
+    #[test]
+    fn replay_reproduces_camera_and_selection() {
+        let mut app = App::new();
+        app.add_plugins(bevy::time::TimePlugin)
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<HoveredHex>()
+            .init_resource::<SelectedHex>()
+            .init_resource::<SelectedRegion>()
+            .init_resource::<InputRecorder>()
+            .init_resource::<CameraMovement>()
+            .add_systems(
+                PreUpdate,
+                (replay_input, toggle_recording, record_input).chain(),
+            )
+            .add_systems(Update, (keyboard_controls, select_hovered));
+        let camera = app
+            .world
+            .spawn((Transform::from_xyz(1., 5., 2.), Camera::default()))
+            .id();
+        let frame = |app: &mut App, millis: u64, keys: &[KeyCode], click: Option<hex::HexCoord>| {
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                millis,
+            )));
+            let mut input = app.world.resource_mut::<Input<KeyCode>>();
+            input.reset_all();
+            for key in keys {
+                input.press(*key);
+            }
+            let mut mouse = app.world.resource_mut::<Input<MouseButton>>();
+            mouse.reset_all();
+            if click.is_some() {
+                mouse.press(MouseButton::Left);
+                app.world.resource_mut::<HoveredHex>().0 = click;
+            }
+            app.update();
+        };
+        frame(&mut app, 16, &[], None);
+        frame(&mut app, 16, &[KeyCode::F9], None);
+        frame(&mut app, 16, &[KeyCode::W], None);
+        frame(&mut app, 33, &[KeyCode::W, KeyCode::A], None);
+        frame(&mut app, 20, &[KeyCode::A], Some(hex::HexCoord::new(2, -1)));
+        frame(&mut app, 16, &[KeyCode::Q], None);
+        // The camera coasts on a little after the recording stops, but playback only covers what was recorded
+        let recorded = *app.world.get::<Transform>(camera).unwrap();
+        frame(&mut app, 16, &[KeyCode::F9], None);
+        let selected = app.world.resource::<SelectedHex>().0;
+        assert_eq!(Some(hex::HexCoord::new(2, -1)), selected);
+        assert_eq!(
+            5,
+            app.world.resource::<InputRecorder>().recording.frames.len()
+        );
+
+        // Wander off somewhere else, then play it all back
+        frame(&mut app, 50, &[KeyCode::S, KeyCode::D], None);
+        app.world.resource_mut::<SelectedHex>().0 = None;
+        assert_ne!(recorded, *app.world.get::<Transform>(camera).unwrap());
+        frame(&mut app, 16, &[KeyCode::F10], None);
+        while app.world.resource::<InputRecorder>().mode != RecorderMode::Idle {
+            app.update();
+        }
+        let replayed = app.world.get::<Transform>(camera).unwrap();
+        assert!(replayed.translation.abs_diff_eq(recorded.translation, 1e-5));
+        assert_eq!(selected, app.world.resource::<SelectedHex>().0);
+    }
+
+    #[test]
+    fn hex_mesh_matches_tile_spacing() {
+        let mesh = generate_hex_mesh(&BevelConfig::default());
+        let Some(VertexAttributeValues::Float32x3(points)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("hex mesh should have positions");
+        };
+        let outer = points
+            .iter()
+            .map(|[x, _, z]| (x * x + z * z).sqrt())
+            .fold(0., f32::max);
+        assert!((outer - geometry::DEFAULT_HEX_RADIUS).abs() < 1e-5);
+
+        // Neighboring tiles are placed exactly two inner radii apart, so their edges meet
+        let origin = hex::HexCoord::origin();
+        let a = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &origin,
+            &[0.; 3],
+        ));
+        let b = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &origin.southeast(),
+            &[0.; 3],
+        ));
+        let inner = outer * geometry::HEX_INNER_RADIUS_RATIO;
+        assert!((a.distance(b) - 2. * inner).abs() < 1e-5);
+    }
+
+    #[test]
+    fn disk_preview_marks_exactly_its_range() {
+        let mut app = App::new();
+        app.init_resource::<PendingAction>()
+            .init_resource::<HoveredHex>()
+            .add_systems(Update, preview_area);
+        for c in hex::HexCoord::origin().range(5) {
+            app.world.spawn(Tile {
+                coord: c,
+                kind: TileKind::Grass,
+                height: 0.,
+                color: Color::WHITE,
+            });
+        }
+        let origin = hex::HexCoord::new(1, -1);
+        app.world.resource_mut::<PendingAction>().0 = Some(AreaAction {
+            origin,
+            shape: AreaShape::Disk(2),
+        });
+        app.update();
+        let previewed: HashSet<_> = app
+            .world
+            .query_filtered::<&Tile, With<AreaPreview>>()
+            .iter(&app.world)
+            .map(|tile| tile.coord)
+            .collect();
+        assert_eq!(
+            origin.range(2).into_iter().collect::<HashSet<_>>(),
+            previewed
+        );
+
+        // A wedge only reaches out the way it's aimed
+        let wedge = AreaShape::Wedge(3).tiles(&origin, Some(origin.north().north()));
+        assert!(wedge.contains(&origin.north()));
+        assert!(!wedge.contains(&origin) && !wedge.contains(&origin.south()));
+        assert!(wedge.iter().all(|c| c.distance(&origin) <= 3));
+
+        // Cancelling clears the preview
+        app.world.resource_mut::<PendingAction>().0 = None;
+        app.update();
+        assert_eq!(
+            0,
+            app.world
+                .query_filtered::<(), With<AreaPreview>>()
+                .iter(&app.world)
+                .count()
+        );
+    }
+
+    #[test]
+    fn shades_come_from_the_palette() {
+        let config = MapConfig {
+            seed: 7,
+            shade_variation: true,
+            ..Default::default()
+        };
+        let tiles = hex::HexCoord::origin().range(6);
+        let mut seen = HashSet::new();
+        for c in &tiles {
+            let color = tile_color(&config, TileKind::Grass, c);
+            assert!(kind_palette(TileKind::Grass).contains(&color));
+            assert_eq!(color, tile_color(&config, TileKind::Grass, c));
+            seen.insert(format!("{color:?}"));
+        }
+        assert!(seen.len() > 1);
+
+        assert_eq!(
+            kind_color(TileKind::Water),
+            tile_color(&config, TileKind::Water, &tiles[3])
+        );
+        let flat = MapConfig {
+            shade_variation: false,
+            ..config
+        };
+        for c in &tiles {
+            assert_eq!(
+                kind_color(TileKind::Hills),
+                tile_color(&flat, TileKind::Hills, c)
+            );
+        }
+    }
+
+    #[test]
+    fn tiles_of_a_shade_share_a_material_until_recolored() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<EditHistory>()
+            .init_resource::<HoveredHex>()
+            .insert_resource(MapConfig {
+                size: 6,
+                background: false,
+                seed: 3,
+                shade_variation: true,
+                ..Default::default()
+            })
+            .init_resource::<RenderQuality>()
+            .init_resource::<BevelConfig>()
+            .add_systems(Startup, sample_level)
+            .add_systems(Update, edit_terrain);
+        app.update();
+
+        // Only the palettes' colors are allocated, however many tiles there are
+        let mut query = app
+            .world
+            .query::<(Entity, &Tile, &Handle<StandardMaterial>)>();
+        let tiles: Vec<_> = query
+            .iter(&app.world)
+            .map(|(entity, tile, handle)| (entity, tile.coord, tile.kind, handle.clone()))
+            .collect();
+        let handles: HashSet<_> = tiles.iter().map(|(.., handle)| handle.clone()).collect();
+        assert!(tiles.len() > 100);
+        assert!(handles.len() <= 3 * PALETTE_SIZE + 1);
+        assert_eq!(
+            handles.len(),
+            app.world.resource::<Assets<StandardMaterial>>().len()
+        );
+
+        // Repainting one tile leaves the others of its shade alone
+        let (edited, coord, kind, shared) = tiles
+            .iter()
+            .find(|(.., kind, _)| *kind == TileKind::Grass)
+            .cloned()
+            .unwrap();
+        let (other, ..) = tiles
+            .iter()
+            .find(|(entity, .., handle)| *entity != edited && *handle == shared)
+            .unwrap();
+        app.world.resource_mut::<HoveredHex>().0 = Some(coord);
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Key4);
+        app.update();
+
+        let color = |app: &App, entity: Entity| {
+            let handle = app.world.get::<Handle<StandardMaterial>>(entity).unwrap();
+            let materials = app.world.resource::<Assets<StandardMaterial>>();
+            materials.get(handle).unwrap().base_color
+        };
+        assert_ne!(kind, TileKind::Hills);
+        assert!(kind_palette(TileKind::Hills).contains(&color(&app, edited)));
+        assert!(kind_palette(TileKind::Grass).contains(&color(&app, *other)));
+        assert_eq!(
+            shared,
+            *app.world.get::<Handle<StandardMaterial>>(*other).unwrap()
+        );
+    }
+
+    #[test]
+    fn shift_click_toggles_region_membership() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<HoveredHex>()
+            .init_resource::<SelectedHex>()
+            .init_resource::<SelectedRegion>()
+            .add_systems(Update, select_hovered);
+        let click = |app: &mut App, c: hex::HexCoord, shift: bool| {
+            let mut keys = app.world.resource_mut::<Input<KeyCode>>();
+            keys.reset_all();
+            if shift {
+                keys.press(KeyCode::ShiftLeft);
+            }
+            let mut mouse = app.world.resource_mut::<Input<MouseButton>>();
+            mouse.reset_all();
+            mouse.press(MouseButton::Left);
+            app.world.resource_mut::<HoveredHex>().0 = Some(c);
+            app.update();
+        };
+        let region = |app: &App| app.world.resource::<SelectedRegion>().0.clone();
+        let (a, b, c) = (
+            hex::HexCoord::origin(),
+            hex::HexCoord::new(1, 0),
+            hex::HexCoord::new(3, -2),
+        );
+
+        click(&mut app, a, false);
+        assert_eq!(HashSet::from([a]), region(&app));
+        click(&mut app, b, true);
+        click(&mut app, c, true);
+        assert_eq!(HashSet::from([a, b, c]), region(&app));
+        // Shift-clicking a tile that's already in takes it back out, without moving the selection
+        click(&mut app, a, true);
+        assert_eq!(HashSet::from([b, c]), region(&app));
+        assert_eq!(Some(a), app.world.resource::<SelectedHex>().0);
+
+        // A plain click starts over
+        click(&mut app, c, false);
+        assert_eq!(HashSet::from([c]), region(&app));
+        assert_eq!(Some(c), app.world.resource::<SelectedHex>().0);
+    }
+
+    #[test]
+    fn camera_eases_in_and_out_of_motion() {
+        let mut app = App::new();
+        app.add_plugins(bevy::time::TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                50,
+            )))
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<CameraMovement>()
+            .add_systems(Update, keyboard_controls);
+        let camera = app
+            .world
+            .spawn((Transform::default(), Camera::default()))
+            .id();
+        let max_speed = app.world.resource::<CameraMovement>().max_speed;
+        let speed = |app: &App| app.world.resource::<CameraMovement>().velocity.x;
+
+        // The first update only starts the clock
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+        let mut speeds = vec![];
+        for _ in 0..8 {
+            app.update();
+            speeds.push(speed(&app));
+        }
+        assert!(speeds[0] > 0. && speeds[0] < max_speed);
+        assert!(speeds.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(max_speed, *speeds.last().unwrap());
+
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::W);
+        app.update();
+        let coasting = speed(&app);
+        assert!(coasting > 0. && coasting < max_speed);
+        for _ in 0..8 {
+            app.update();
+        }
+        assert_eq!(Vec3::ZERO, app.world.resource::<CameraMovement>().velocity);
+        let stopped = app.world.get::<Transform>(camera).unwrap().translation;
+        app.update();
+        assert_eq!(
+            stopped,
+            app.world.get::<Transform>(camera).unwrap().translation
+        );
+    }
+
+    #[test]
+    fn reflection_camera_mirrors_the_main_camera() {
+        let mut app = App::new();
+        app.insert_resource(WaterLevel(0.25))
+            .add_systems(Update, follow_reflection);
+        let main = Transform::from_xyz(-10., 15., 3.).looking_at(Vec3::new(2., 0., -1.), Vec3::Y);
+        app.world.spawn((main, Camera3d::default()));
+        let reflection = app
+            .world
+            .spawn((Transform::default(), Camera3d::default(), ReflectionCamera))
+            .id();
+        let surface = app
+            .world
+            .spawn((Transform::default(), ReflectionSurface))
+            .id();
+        app.update();
+
+        let mirrored = app.world.get::<Transform>(reflection).unwrap();
+        // Just as far beneath the water as the main camera is above it
+        assert!(mirrored
+            .translation
+            .abs_diff_eq(Vec3::new(-10., 2. * 0.25 - 15., 3.), 1e-5));
+        // Looking up at the reflection of whatever the main camera looks down at
+        let target = Vec3::new(2., 2. * 0.25, -1.);
+        let toward = (target - mirrored.translation).normalize();
+        assert!(mirrored.forward().abs_diff_eq(toward, 1e-5));
+        // Still upright, and with left and right the same way round
+        assert!(mirrored.up().y > 0.);
+        assert!(mirrored.right().abs_diff_eq(main.right(), 1e-5));
+        assert_eq!(
+            0.25,
+            app.world.get::<Transform>(surface).unwrap().translation.y
+        );
+    }
+
+    #[test]
+    fn clicking_a_tile_sends_an_event() {
+        let mut app = App::new();
+        app.init_resource::<Input<MouseButton>>()
+            .init_resource::<HoveredHex>()
+            .add_event::<TileClicked>()
+            .add_systems(Update, emit_tile_clicks);
+        let clicks = |app: &mut App| -> Vec<TileClicked> {
+            let mut events = app.world.resource_mut::<Events<TileClicked>>();
+            events.drain().collect()
+        };
+
+        // Clicking nothing sends nothing
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .press(MouseButton::Left);
+        app.update();
+        assert!(clicks(&mut app).is_empty());
+
+        let coord = hex::HexCoord::new(2, -4);
+        app.world.resource_mut::<HoveredHex>().0 = Some(coord);
+        let mut mouse = app.world.resource_mut::<Input<MouseButton>>();
+        mouse.reset_all();
+        mouse.press(MouseButton::Right);
+        app.update();
+        assert_eq!(
+            vec![TileClicked {
+                coord,
+                button: MouseButton::Right
+            }],
+            clicks(&mut app)
+        );
+
+        // Holding the button down doesn't keep clicking
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .clear_just_pressed(MouseButton::Right);
+        app.update();
+        assert!(clicks(&mut app).is_empty());
+    }
+
+    #[test]
+    fn chunked_land_is_drawn_by_its_chunks() {
+        let config = MapConfig {
+            size: 20,
+            background: false,
+            chunked: true,
+            ..Default::default()
+        };
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<Assets<Image>>()
+            .insert_resource(config)
+            .init_resource::<RenderQuality>()
+            .init_resource::<BevelConfig>()
+            .add_systems(Startup, sample_level);
+        app.update();
+
+        let tiles: Vec<_> = app
+            .world
+            .query::<(&Tile, Option<&Handle<Mesh>>)>()
+            .iter(&app.world)
+            .map(|(tile, mesh)| (tile.kind, mesh.is_some()))
+            .collect();
+        assert_eq!(40 * 40, tiles.len());
+        // Only water draws itself
+        for (kind, drawn) in &tiles {
+            assert_eq!(*kind == TileKind::Water, *drawn);
+        }
+
+        let land = tiles
+            .iter()
+            .filter(|(kind, _)| *kind != TileKind::Water)
+            .count();
+        let per_tile = generate_hex_mesh(&BevelConfig::default()).count_vertices();
+        let mut chunks = app.world.query_filtered::<&Handle<Mesh>, With<TileChunk>>();
+        let meshes = app.world.resource::<Assets<Mesh>>();
+        let vertices: usize = chunks
+            .iter(&app.world)
+            .map(|handle| meshes.get(handle).unwrap().count_vertices())
+            .sum();
+        assert_eq!(land * per_tile, vertices);
+        // Coordinates from -20 to 19 fall into four chunks along each axis
+        assert!(chunks.iter(&app.world).count() <= 16);
+    }
+
+    #[test]
+    fn chunks_follow_their_tiles() {
+        let config = MapConfig {
+            size: 8,
+            seed: 3,
+            background: false,
+            chunked: true,
+            ..Default::default()
+        };
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<Assets<Image>>()
+            .insert_resource(config)
+            .init_resource::<RenderQuality>()
+            .init_resource::<BevelConfig>()
+            .add_systems(Startup, sample_level)
+            .add_systems(PostUpdate, rebuild_chunks);
+        app.update();
+
+        let (entity, coord) = app
+            .world
+            .query_filtered::<(Entity, &Tile), With<Chunked>>()
+            .iter(&app.world)
+            .map(|(entity, tile)| (entity, tile.coord))
+            .next()
+            .unwrap();
+        // Each tile's first point in its chunk is the middle of its top, at the tile's translation
+        let drawn_at = |app: &mut App, at: Vec3| {
+            let handle = app
+                .world
+                .query::<(&TileChunk, &Handle<Mesh>)>()
+                .iter(&app.world)
+                .find(|(chunk, _)| chunk.0 == chunk_of(&coord))
+                .map(|(_, handle)| handle.clone())
+                .unwrap();
+            let mesh = app.world.resource::<Assets<Mesh>>().get(&handle).unwrap();
+            let Some(VertexAttributeValues::Float32x3(positions)) =
+                mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+            else {
+                panic!("chunk has no positions");
+            };
+            let Some(VertexAttributeValues::Float32x4(colors)) =
+                mesh.attribute(Mesh::ATTRIBUTE_COLOR)
+            else {
+                panic!("chunk has no colors");
+            };
+            positions
+                .iter()
+                .position(|p| Vec3::from(*p).distance(at) < 1e-5)
+                .map(|i| colors[i])
+        };
+
+        // Jitter nudges chunked tiles just like the rest
+        let start = app.world.get::<Transform>(entity).unwrap().translation;
+        let flat = geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &coord,
+            &[0., start.y, 0.],
+        );
+        assert_ne!(Vec3::from(flat), start);
+        let color = app.world.get::<Tile>(entity).unwrap().color;
+        assert_eq!(Some(color.as_rgba_f32()), drawn_at(&mut app, start));
+
+        // Raising the tile, like an edit easing it up, moves it in its chunk
+        let raised = start + Vec3::Y;
+        app.world.get_mut::<Transform>(entity).unwrap().translation = raised;
+        app.update();
+        assert_eq!(None, drawn_at(&mut app, start));
+        assert_eq!(Some(color.as_rgba_f32()), drawn_at(&mut app, raised));
+
+        // And flooding it turns it to water
+        app.world.entity_mut(entity).insert(Water);
+        app.update();
+        assert_eq!(
+            Some(kind_color(TileKind::Water).as_rgba_f32()),
+            drawn_at(&mut app, raised)
+        );
+    }
+
+    #[test]
+    fn every_water_tile_ripples() {
+        // A pond small enough to ripple on one thread, and a sea big enough to be split up
+        for radius in [3, 30] {
+            let count = 1 + 3 * radius * (radius + 1);
+            assert_eq!(
+                radius == 30,
+                count >= PARALLEL_RIPPLE_MIN_TILES,
+                "radius {radius}"
+            );
+            ripple_sea(radius);
+        }
+    }
+
+    /// Time both ways of rippling seas of a few sizes, to see where handing the work out starts to pay off.
+    /// Run it on its own, in release, with `cargo test --release ripple_benchmark -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn ripple_benchmark() {
+        use bevy::tasks::{ComputeTaskPool, TaskPoolBuilder};
+        use std::time::Instant;
+
+        const THREADS: usize = 4;
+        const RUNS: u32 = 200;
+        ComputeTaskPool::get_or_init(|| TaskPoolBuilder::new().num_threads(THREADS).build());
+        println!("tiles  one thread  {THREADS} threads  (microseconds per frame)");
+        for radius in [4, 9, 18, 26, 36, 52, 73] {
+            let mut timings = [0.; 2];
+            for (parallel, timing) in [false, true].into_iter().zip(&mut timings) {
+                let mut world = World::new();
+                world.init_resource::<Time>();
+                world.insert_resource(WaterLevel(-0.2));
+                for c in hex::HexCoord::origin().range(radius) {
+                    let pos = geometry::center_with_layout(
+                        MAP_LAYOUT,
+                        geometry::DEFAULT_HEX_RADIUS,
+                        &c,
+                        &[0., 0., 0.],
+                    );
+                    world.spawn((Transform::from_translation(Vec3::from(pos)), Water));
+                }
+                let mut schedule = Schedule::default();
+                schedule.add_systems(
+                    move |time: Res<Time>,
+                          level: Res<WaterLevel>,
+                          mut q: Query<&mut Transform, With<Water>>| {
+                        ripple_tiles(&time, &level, &mut q, parallel);
+                    },
+                );
+                // Warm up, so the schedule's first run doesn't count
+                schedule.run(&mut world);
+                let start = Instant::now();
+                for _ in 0..RUNS {
+                    schedule.run(&mut world);
+                }
+                *timing = start.elapsed().as_secs_f64() * 1e6 / RUNS as f64;
+            }
+            let tiles = 1 + 3 * radius * (radius + 1);
+            println!("{tiles:>5}  {:>10.1}  {:>9.1}", timings[0], timings[1]);
+        }
+    }
+
+    fn ripple_sea(radius: usize) {
+        let mut app = App::new();
+        app.add_plugins((
+            bevy::core::TaskPoolPlugin::default(),
+            bevy::time::TimePlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            750,
+        )))
+        .insert_resource(WaterLevel(-0.2))
+        .add_systems(Update, water_ripple);
+        let sea: Vec<_> = hex::HexCoord::origin()
+            .range(radius)
+            .into_iter()
+            .map(|c| {
+                let pos = geometry::center_with_layout(
+                    MAP_LAYOUT,
+                    geometry::DEFAULT_HEX_RADIUS,
+                    &c,
+                    &[0., 0., 0.],
+                );
+                app.world
+                    .spawn((Transform::from_translation(Vec3::from(pos)), Water))
+                    .id()
+            })
+            .collect();
+        let land = app.world.spawn(Transform::from_xyz(1., 0.5, 1.)).id();
+        app.update();
+        app.update();
+
+        let seconds = app.world.resource::<Time>().elapsed_seconds();
+        assert!(seconds > 0.);
+        for tile in sea {
+            let t = app.world.get::<Transform>(tile).unwrap().translation;
+            assert_eq!(-0.2 + ripple_offset(seconds, t.x, t.z), t.y);
+        }
+        assert_eq!(0.5, app.world.get::<Transform>(land).unwrap().translation.y);
+    }
+
+    #[test]
+    fn camera_settles_over_a_tile_once_stopped() {
+        let mut app = App::new();
+        app.add_plugins(bevy::time::TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                50,
+            )))
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<CameraMovement>()
+            .add_systems(
+                Update,
+                (toggle_camera_snap, keyboard_controls, snap_camera_to_tile).chain(),
+            );
+        let looking_at = Vec3::new(1.2, 0., 0.7);
+        let camera = app
+            .world
+            .spawn((
+                Transform::from_xyz(-4.6, 8., 1.3).looking_at(looking_at, Vec3::Y),
+                Camera::default(),
+            ))
+            .id();
+        let ground = |app: &App| {
+            let transform = app.world.get::<Transform>(camera).unwrap();
+            ground_intersection(transform.translation, transform.forward()).unwrap()
+        };
+
+        // Left alone, the camera stays where it is
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(ground(&app).distance(looking_at) < 1e-5);
+
+        let c = geometry::hex_at_point(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &looking_at.to_array(),
+        );
+        let target = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &c,
+            &[0., 0., 0.],
+        ));
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::G);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().clear();
+        let first = ground(&app).distance(target);
+        assert!(first < looking_at.distance(target));
+        for _ in 0..60 {
+            app.update();
+        }
+        assert!(ground(&app).distance(target) < 1e-3);
+        // Only sideways, the camera stays at the same height
+        assert_eq!(
+            8.,
+            app.world.get::<Transform>(camera).unwrap().translation.y
+        );
+    }
+
+    #[test]
+    fn bevel_factor_stays_in_range() {
+        let with = |factor| BevelConfig {
+            factor,
+            ..Default::default()
+        };
+        assert_eq!(MIN_BEVEL_FACTOR, with(0.).factor());
+        assert_eq!(MIN_BEVEL_FACTOR, with(-2.).factor());
+        assert_eq!(1., with(1.5).factor());
+        assert_eq!(1., with(f32::NAN).factor());
+        assert_eq!(0.7, with(0.7).factor());
+
+        // Even asking for no top at all leaves a face that's more than a point
+        let mesh = generate_hex_mesh(&with(0.));
+        let Some(VertexAttributeValues::Float32x3(points)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("hex mesh should have positions");
+        };
+        let [x, _, z] = points[1];
+        assert!((x * x + z * z).sqrt() > 0.);
+    }
+
+    #[test]
+    fn changing_the_bevel_rebuilds_the_shared_mesh() {
+        let mut app = App::new();
+        app.init_resource::<Assets<Mesh>>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<BevelConfig>()
+            .add_systems(
+                Update,
+                (
+                    adjust_bevel,
+                    rebuild_hex_meshes.run_if(resource_changed::<BevelConfig>()),
+                )
+                    .chain(),
+            );
+        let mut meshes = app.world.resource_mut::<Assets<Mesh>>();
+        let land = meshes.add(Mesh::new(PrimitiveTopology::TriangleList));
+        let water = meshes.add(Mesh::new(PrimitiveTopology::TriangleList));
+        app.insert_resource(LandMesh(land.clone()))
+            .insert_resource(WaterMesh {
+                mesh: water.clone(),
+                uvs: vec![],
+            });
+        let top_corner = |app: &App| {
+            let meshes = app.world.resource::<Assets<Mesh>>();
+            match meshes
+                .get(&land)
+                .unwrap()
+                .attribute(Mesh::ATTRIBUTE_POSITION)
+            {
+                Some(VertexAttributeValues::Float32x3(points)) => Vec3::from(points[1]).length(),
+                _ => 0.,
+            }
+        };
+        app.update();
+        let before = top_corner(&app);
+        assert!((before - geometry::DEFAULT_BEVEL_FACTOR).abs() < 1e-5);
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::B);
+        app.update();
+        let after = top_corner(&app);
+        assert!((after - (geometry::DEFAULT_BEVEL_FACTOR - 0.1)).abs() < 1e-5);
+        let meshes = app.world.resource::<Assets<Mesh>>();
+        assert!(meshes.get(&water).unwrap().count_vertices() > 0);
+    }
+
+    #[test]
+    fn picking_clears_the_hover_when_the_cursor_leaves() {
+        let mut app = App::new();
+        app.insert_resource(HoveredHex(Some(hex::HexCoord::new(1, 1))))
+            .add_systems(PreUpdate, picking);
+        app.world.spawn((Window::default(), PrimaryWindow));
+        app.world.spawn((Camera3dBundle::default(),));
+        app.update();
+        assert_eq!(HoveredHex(None), *app.world.resource::<HoveredHex>());
+    }
+
+    #[test]
+    fn picking_finds_the_tile_under_the_cursor() {
+        use bevy::render::camera::{camera_system, ManualTextureViews};
+        use bevy::window::{WindowCreated, WindowResized};
+
+        let mut app = App::new();
+        app.add_event::<WindowCreated>()
+            .add_event::<WindowResized>()
+            .add_event::<AssetEvent<Image>>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<ManualTextureViews>()
+            .init_resource::<HoveredHex>()
+            // The camera needs to know the size of its window before it can cast rays
+            .add_systems(Update, (camera_system::<Projection>, picking).chain());
+        let mut window = Window::default();
+        let middle = Vec2::new(window.width(), window.height()) / 2.;
+        window.set_cursor_position(Some(middle));
+        app.world.spawn((window, PrimaryWindow));
+
+        // Looking straight down on a tile, so the middle of the window is right over it
+        let c = hex::HexCoord::new(2, -1);
+        let below = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &c,
+            &[0., 0., 0.],
+        ));
+        let transform =
+            Transform::from_translation(below + Vec3::Y * 10.).looking_at(below, Vec3::NEG_Z);
+        app.world.spawn(Camera3dBundle {
+            transform,
+            global_transform: transform.into(),
+            ..Default::default()
+        });
+        app.update();
+        assert_eq!(HoveredHex(Some(c)), *app.world.resource::<HoveredHex>());
+
+        // Moving the cursor over the middle of a neighbor picks that instead
+        let n = c.north();
+        let neighbor_middle = Vec3::from(geometry::center_with_layout(
+            MAP_LAYOUT,
+            geometry::DEFAULT_HEX_RADIUS,
+            &n,
+            &[0., 0., 0.],
+        ));
+        let mut cameras = app.world.query::<(&Camera, &GlobalTransform)>();
+        let (camera, transform) = cameras.single(&app.world);
+        let cursor = camera
+            .world_to_viewport(transform, neighbor_middle)
+            .unwrap();
+        let mut windows = app.world.query::<&mut Window>();
+        windows
+            .single_mut(&mut app.world)
+            .set_cursor_position(Some(cursor));
+        app.update();
+        assert_eq!(HoveredHex(Some(n)), *app.world.resource::<HoveredHex>());
+    }
+
+    #[test]
+    fn coordinate_readout_shows_the_hovered_hex() {
+        let mut app = App::new();
+        let c = hex::HexCoord::new(2, -1);
+        app.insert_resource(HoveredHex(Some(c)))
+            .add_systems(Startup, spawn_coordinate_readout)
+            .add_systems(Update, coordinate_readout);
+        let mut window = Window::default();
+        window.set_cursor_position(Some(Vec2::new(100., 50.)));
+        app.world.spawn((window, PrimaryWindow));
+        app.update();
+
+        let readout = |app: &mut App| {
+            let mut query = app
+                .world
+                .query_filtered::<(&Text, &Style, &Visibility), With<CoordinateReadout>>();
+            let (text, style, visibility) = query.single(&app.world);
+            (text.sections[0].value.clone(), style.left, *visibility)
+        };
+        assert_eq!(
+            (c.to_string(), Val::Px(116.), Visibility::Inherited),
+            readout(&mut app)
+        );
+
+        // With no tile hovered, the label hides rather than showing a stale coordinate
+        app.world.resource_mut::<HoveredHex>().0 = None;
+        app.update();
+        assert_eq!(Visibility::Hidden, readout(&mut app).2);
+    }
+
+    #[test]
+    fn hovered_tile_swaps_in_a_highlight() {
+        let mut app = App::new();
+        app.init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<MaterialCache>()
+            .init_resource::<HoveredHex>()
+            .init_resource::<TileIndex>()
+            .add_systems(Update, (index_tiles, highlight_hovered_tile).chain());
+        let coords = [
+            hex::HexCoord::new(0, 0),
+            hex::HexCoord::new(1, 0),
+            hex::HexCoord::new(0, 1),
+        ];
+        // Every tile starts on the same shared material, like freshly spawned ones
+        let color = kind_color(TileKind::Grass);
+        let shared = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(color.into());
+        app.world
+            .resource_mut::<MaterialCache>()
+            .shared
+            .insert(color.as_rgba_u32(), shared.clone());
+        let tiles: Vec<_> = coords
+            .iter()
+            .map(|c| {
+                let tile = Tile {
+                    coord: *c,
+                    kind: TileKind::Grass,
+                    height: 0.,
+                    color,
+                };
+                app.world.spawn((tile, shared.clone())).id()
+            })
+            .collect();
+        let handle_of = |app: &App, entity: Entity| {
+            app.world
+                .get::<Handle<StandardMaterial>>(entity)
+                .unwrap()
+                .clone()
+        };
+        let color_of = |app: &App, handle: &Handle<StandardMaterial>| {
+            let materials = app.world.resource::<Assets<StandardMaterial>>();
+            materials.get(handle).unwrap().base_color
+        };
+        let hover = |app: &mut App, c: Option<hex::HexCoord>| {
+            app.world.resource_mut::<HoveredHex>().0 = c;
+            app.update();
+            tiles
+                .iter()
+                .copied()
+                .filter(|entity| handle_of(app, *entity) != shared)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec![tiles[0]], hover(&mut app, Some(coords[0])));
+        let highlight = handle_of(&app, tiles[0]);
+        assert_eq!(
+            lerp_color(color, Color::WHITE, HOVER_BRIGHTEN),
+            color_of(&app, &highlight)
+        );
+        // The shared material itself is never touched
+        assert_eq!(color, color_of(&app, &shared));
+
+        // Moving on puts the first tile back on the shared material, and lights the next with the same highlight
+        assert_eq!(vec![tiles[1]], hover(&mut app, Some(coords[1])));
+        assert_eq!(highlight, handle_of(&app, tiles[1]));
+        // Passing over several tiles in one frame leaves only the last one lit
+        app.world.resource_mut::<HoveredHex>().0 = Some(coords[0]);
+        assert_eq!(vec![tiles[2]], hover(&mut app, Some(coords[2])));
+        // Off the grid, nothing is lit
+        assert!(hover(&mut app, Some(hex::HexCoord::new(9, 9))).is_empty());
+        assert_eq!(vec![tiles[0]], hover(&mut app, Some(coords[0])));
+        assert_eq!(2, app.world.resource::<Assets<StandardMaterial>>().len());
+
+        // A flashing tile is put back on its own material, and left alone until the flash is over
+        app.world
+            .entity_mut(tiles[0])
+            .insert(TileFlash::new(Color::RED, 1.));
+        assert!(hover(&mut app, Some(coords[0])).is_empty());
+        app.world.entity_mut(tiles[0]).remove::<TileFlash>();
+        assert_eq!(vec![tiles[0]], hover(&mut app, Some(coords[0])));
+
+        // An edit gives the hovered tile a material of its own in a new color, which is lit up and put back in turn
+        let hills = kind_color(TileKind::Hills);
+        let recolored = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(hills.into());
+        *app.world
+            .get_mut::<Handle<StandardMaterial>>(tiles[0])
+            .unwrap() = recolored.clone();
+        app.update();
+        let lit = handle_of(&app, tiles[0]);
+        assert_ne!(recolored, lit);
+        assert_eq!(
+            lerp_color(hills, Color::WHITE, HOVER_BRIGHTEN),
+            color_of(&app, &lit)
+        );
+        assert_eq!(vec![tiles[0]], hover(&mut app, None));
+        assert_eq!(recolored, handle_of(&app, tiles[0]));
+    }
+
+    #[test]
+    fn orbiting_circles_the_focus_without_flipping() {
+        let settings = OrbitSettings::default();
+        let focus = Vec3::new(2., 0., -1.);
+        let start = Transform::from_xyz(-6., 9., 3.).looking_at(focus, Vec3::Y);
+        let distance = start.translation.distance(focus);
+
+        // Swinging sideways keeps the same height and distance, and keeps looking at the same spot
+        let swung = orbit(&start, Vec2::new(300., 0.), &settings);
+        assert!((swung.translation.y - start.translation.y).abs() < 1e-3);
+        assert!((swung.translation.distance(focus) - distance).abs() < 1e-3);
+        let ground = ground_intersection(swung.translation, swung.forward()).unwrap();
+        assert!(ground.distance(focus) < 1e-3);
+
+        // However far it's dragged, the camera stops short of the top and the ground
+        let pitch =
+            |t: &Transform| ((t.translation.y - focus.y) / t.translation.distance(focus)).asin();
+        let over = orbit(&start, Vec2::new(0., 1e5), &settings);
+        assert!((pitch(&over) - settings.max_pitch).abs() < 1e-3);
+        assert!(over.forward().y < 0.);
+        let under = orbit(&start, Vec2::new(0., -1e5), &settings);
+        assert!((pitch(&under) - settings.min_pitch).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tab_swaps_camera_modes() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<CameraMode>()
+            .insert_resource(CameraMovement {
+                velocity: Vec3::X,
+                ..Default::default()
+            })
+            .add_systems(Update, toggle_camera_mode);
+        app.update();
+        assert_eq!(CameraMode::Fly, *app.world.resource::<CameraMode>());
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Tab);
+        app.update();
+        assert_eq!(CameraMode::Orbit, *app.world.resource::<CameraMode>());
+        assert_eq!(Vec3::ZERO, app.world.resource::<CameraMovement>().velocity);
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.reset_all();
+        input.press(KeyCode::Tab);
+        app.update();
+        assert_eq!(CameraMode::Fly, *app.world.resource::<CameraMode>());
+    }
+
+    #[test]
+    fn scrolling_zooms_within_limits() {
+        let mut app = App::new();
+        app.init_resource::<ZoomLimits>()
+            .add_event::<MouseWheel>()
+            .add_systems(Update, camera_zoom);
+        let focus = Vec3::new(1., 0., 2.);
+        let start = Transform::from_xyz(-7., 6., 2.).looking_at(focus, Vec3::Y);
+        let camera = app.world.spawn((start, Camera::default())).id();
+        let window = app.world.spawn_empty().id();
+        let scroll = |app: &mut App, unit, steps: &[f32]| {
+            for y in steps {
+                app.world.send_event(MouseWheel {
+                    unit,
+                    x: 0.,
+                    y: *y,
+                    window,
+                });
+            }
+            app.update();
+            *app.world.get::<Transform>(camera).unwrap()
+        };
+        let distance = start.translation.distance(focus);
+
+        // A trackpad's many little scrolls add up to the same as one click of a wheel
+        let pixels = [PIXELS_PER_LINE / 4.; 4];
+        let zoomed = scroll(&mut app, MouseScrollUnit::Pixel, &pixels);
+        assert!(
+            (zoomed.translation.distance(focus) - distance * (1. - ZOOM_PER_LINE)).abs() < 1e-4
+        );
+        assert_eq!(start.rotation, zoomed.rotation);
+        assert!(zoomed.forward().abs_diff_eq(start.forward(), 1e-6));
 
-        let ripple1 = (time / 2. + (x / 3.) + (z / 3.)).sin() * 0.1 - 0.05;
-        let ripple2 = (time + (x / 3.) - (z / 4.)).cos() * 0.1 - 0.05;
-        let ripple3 = (time * 2. + (x / 5.) - (z / 7.)).sin() * 0.1 - 0.05;
-        t.translation = Vec3::new(x, ripple1 + ripple2 + ripple3, z);
+        // And however far it's scrolled, it stops at the limits
+        let limits = ZoomLimits::default();
+        let closest = scroll(&mut app, MouseScrollUnit::Line, &[500.]);
+        assert!((closest.translation.distance(focus) - limits.min).abs() < 1e-3);
+        let furthest = scroll(&mut app, MouseScrollUnit::Line, &[-500.]);
+        assert!((furthest.translation.distance(focus) - limits.max).abs() < 1e-2);
     }
 }